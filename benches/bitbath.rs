@@ -0,0 +1,31 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use generalized_heat_engine::{generate_random_permutation, permute, BitBath, SimConfig};
+
+fn bench_representations(c: &mut Criterion) {
+    let n = SimConfig::default().bath_size;
+    let bits: Vec<bool> = (0..n).map(|i| i < n / 2).collect();
+    let perm = generate_random_permutation(n, 0);
+
+    let mut group = c.benchmark_group("bath_representation");
+
+    let mut vec_bath = bits.clone();
+    group.bench_function("vec_bool/permute", |b| {
+        b.iter(|| permute(black_box(&perm), &mut vec_bath))
+    });
+    group.bench_function("vec_bool/count", |b| {
+        b.iter(|| black_box(&vec_bath).iter().filter(|&&x| x).count())
+    });
+
+    let mut bit_bath = BitBath::from(bits);
+    group.bench_function("bitbath/permute", |b| {
+        b.iter(|| bit_bath.permute(black_box(&perm)))
+    });
+    group.bench_function("bitbath/count", |b| {
+        b.iter(|| black_box(&bit_bath).count_ones())
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_representations);
+criterion_main!(benches);
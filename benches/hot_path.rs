@@ -0,0 +1,80 @@
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+use generalized_heat_engine::{
+    default_rules, generate_random_permutation, permute, BitBath, Rule, SimConfig, Simulation,
+    WeirdPermute, World,
+};
+
+const SIZES: [usize; 3] = [20, 200, 2000];
+const RUN_STEPS: u64 = 10_000;
+
+/// `permute` on `Vec<bool>` and `BitBath::permute`, in cells per second.
+fn bench_permute(c: &mut Criterion) {
+    let mut group = c.benchmark_group("permute");
+    for n in SIZES {
+        let bits: Vec<bool> = (0..n).map(|i| i < n / 2).collect();
+        let perm = generate_random_permutation(n, 0);
+        group.throughput(Throughput::Elements(n as u64));
+
+        let mut vec_bath = bits.clone();
+        group.bench_with_input(BenchmarkId::new("vec_bool", n), &perm, |b, perm| {
+            b.iter(|| permute(black_box(perm), &mut vec_bath))
+        });
+        let mut bit_bath = BitBath::from(bits);
+        group.bench_with_input(BenchmarkId::new("bitbath", n), &perm, |b, perm| {
+            b.iter(|| bit_bath.permute(black_box(perm)))
+        });
+    }
+    group.finish();
+}
+
+/// One `WeirdPermute::step` over a world with the default battery and baths
+/// of each size, including generating the permutations.
+fn bench_weird_permute_step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("weird_permute_step");
+    for n in SIZES {
+        let world = World::from_config(&SimConfig {
+            bath_size: n,
+            ..SimConfig::default()
+        });
+        group.throughput(Throughput::Elements(world.subsystems().len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &world, |b, world| {
+            // A fresh rule each time so the permutation cache doesn't help.
+            b.iter_batched(
+                || (WeirdPermute::new(0), world.clone()),
+                |(rule, mut world)| rule.step(&mut world),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+/// `RUN_STEPS` steps of the default rule set on the default world, in steps
+/// per second.
+fn bench_default_run(c: &mut Criterion) {
+    let mut group = c.benchmark_group("default_rules");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(RUN_STEPS));
+    let world = World::from_config(&SimConfig::default());
+    group.bench_function(BenchmarkId::new("steps", RUN_STEPS), |b| {
+        b.iter_batched(
+            || Simulation::new(world.clone(), default_rules(0)),
+            |mut sim| {
+                sim.step_n(RUN_STEPS);
+                sim
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_permute,
+    bench_weird_permute_step,
+    bench_default_run
+);
+criterion_main!(benches);
@@ -0,0 +1,46 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use generalized_heat_engine::{
+    fisher_yates_lcg, generate_random_permutation, Rule, SimConfig, WeirdPermute, World,
+};
+
+const STEPS: i64 = 100;
+
+/// `STEPS` steps of a fresh `WeirdPermute`, with and without the
+/// permutations precomputed.
+fn bench_cache(c: &mut Criterion) {
+    let world = World::from_config(&SimConfig::default());
+    let run = |permute: &WeirdPermute| {
+        let mut world = world.clone();
+        for _ in 0..STEPS {
+            permute.step(&mut world);
+            world.t += 1;
+        }
+        world
+    };
+
+    let mut group = c.benchmark_group("weird_permute");
+    group.bench_function("uncached", |b| {
+        b.iter_batched(|| WeirdPermute::new(0), |p| run(&p), BatchSize::SmallInput)
+    });
+    let precomputed = WeirdPermute::new(0);
+    precomputed.precompute(&world, 0..STEPS);
+    group.bench_function("precomputed", |b| b.iter(|| run(&precomputed)));
+    group.finish();
+}
+
+/// The two ways of turning a seed into a permutation, at a few sizes.
+fn bench_shuffle(c: &mut Criterion) {
+    let mut group = c.benchmark_group("shuffle");
+    for n in [20, 200, 2000] {
+        group.bench_with_input(BenchmarkId::new("std_rng", n), &n, |b, &n| {
+            b.iter(|| generate_random_permutation(n, 7))
+        });
+        group.bench_with_input(BenchmarkId::new("lcg", n), &n, |b, &n| {
+            b.iter(|| fisher_yates_lcg(n, 7))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_cache, bench_shuffle);
+criterion_main!(benches);
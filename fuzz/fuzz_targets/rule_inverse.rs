@@ -0,0 +1,240 @@
+//! Builds a world and a composed rule set from the input bytes, runs it
+//! forward some steps and back as many, and checks the world came back.
+//!
+//! Run it from the repository root with
+//!
+//! ```sh
+//! cargo install cargo-fuzz
+//! cargo +nightly fuzz run rule_inverse
+//! ```
+//!
+//! A failing input is saved under `fuzz/artifacts/rule_inverse/`; replay it
+//! with `cargo +nightly fuzz run rule_inverse <path>`.
+
+#![no_main]
+
+use std::rc::Rc;
+
+use generalized_heat_engine::{
+    BathId, BlockRule, CNot, ColdCouple, CondSwap, Conditional, ConditionalBathSwap,
+    ControlledSwap, CyclicShift, NoOp, OddEvenStep, Permute, ProbeAndSwap, ReflectBath, Repeat,
+    Rule, Scheduled, SeededPermute, Shuffle, Simulation, StochasticSwap, Then, Thermalize,
+    WeirdPermute, World,
+};
+use libfuzzer_sys::fuzz_target;
+
+/// Reads numbers off the front of the input, yielding zeros once it runs
+/// out so every input decodes to something.
+struct Input<'a>(&'a [u8]);
+
+impl Input<'_> {
+    fn byte(&mut self) -> u8 {
+        let Some((&b, rest)) = self.0.split_first() else {
+            return 0;
+        };
+        self.0 = rest;
+        b
+    }
+
+    fn u64(&mut self) -> u64 {
+        (0..8).fold(0, |acc, _| acc << 8 | self.byte() as u64)
+    }
+
+    fn bool(&mut self) -> bool {
+        self.byte() & 1 == 1
+    }
+
+    /// A number in `0..n`; `n` must be positive.
+    fn below(&mut self, n: usize) -> usize {
+        (u16::from_be_bytes([self.byte(), self.byte()]) as usize) % n
+    }
+
+    fn bath(&mut self) -> BathId {
+        [BathId::Battery, BathId::Hot, BathId::Cold][self.below(3)]
+    }
+
+    fn cells(&mut self, len: usize) -> Vec<bool> {
+        (0..len).map(|_| self.bool()).collect()
+    }
+
+    /// A cell index in `bath`, which must be non-empty.
+    fn cell(&mut self, world: &World, bath: BathId) -> (BathId, usize) {
+        (bath, self.below(world.subsystem(bath).len()))
+    }
+
+    fn any_cell(&mut self, world: &World) -> (BathId, usize) {
+        let bath = self.bath();
+        self.cell(world, bath)
+    }
+
+    fn permutation(&mut self, len: usize) -> Vec<usize> {
+        let shuffle = if self.bool() {
+            Shuffle::Lcg
+        } else {
+            Shuffle::StdRng
+        };
+        shuffle.permutation(len, self.u64())
+    }
+}
+
+fn world(input: &mut Input) -> World {
+    let battery = 1 + input.below(16);
+    let hot = 2 + input.below(40);
+    let cold = if input.bool() {
+        hot
+    } else {
+        1 + input.below(40)
+    };
+    World::builder()
+        .t(input.below(200) as i64 - 100)
+        .battery(input.cells(battery))
+        .hot_bath(input.cells(hot))
+        .cold_bath(input.cells(cold))
+        .build()
+}
+
+/// Some rule that fits `world`, or `None` if the bytes picked an invalid
+/// combination of indices. Composite rules recurse up to `depth` levels.
+fn rule(input: &mut Input, world: &World, depth: u32) -> Option<Box<dyn Rule>> {
+    let kinds = if depth == 0 { 18 } else { 22 };
+    Some(match input.below(kinds) {
+        0 => Box::new(NoOp),
+        1 => Box::new(CondSwap),
+        2 => {
+            let (_, probe) = input.cell(world, BathId::Hot);
+            let (_, battery_slot) = input.cell(world, BathId::Battery);
+            let (_, hot_slot) = input.cell(world, BathId::Hot);
+            if probe == hot_slot {
+                return None;
+            }
+            Box::new(ProbeAndSwap::new(probe, battery_slot, hot_slot))
+        }
+        3 => {
+            let (_, probe) = input.cell(world, BathId::Cold);
+            let (_, battery_slot) = input.cell(world, BathId::Battery);
+            let (_, cold_slot) = input.cell(world, BathId::Cold);
+            if probe == cold_slot {
+                return None;
+            }
+            Box::new(ColdCouple::new(probe, battery_slot, cold_slot))
+        }
+        4 => {
+            let (control, a, b) = (
+                input.any_cell(world),
+                input.any_cell(world),
+                input.any_cell(world),
+            );
+            if control == a || control == b {
+                return None;
+            }
+            Box::new(ControlledSwap::new(control, a, b))
+        }
+        5 => {
+            let (control, target) = (input.any_cell(world), input.any_cell(world));
+            if control == target {
+                return None;
+            }
+            Box::new(CNot::new(control, target))
+        }
+        6 => Box::new(ConditionalBathSwap::new(input.cell(world, BathId::Battery))),
+        7 => Box::new(
+            Permute::new(
+                input.permutation(world.battery().len()),
+                input.permutation(world.hot_bath().len()),
+                input.permutation(world.cold_bath().len()),
+            )
+            .ok()?,
+        ),
+        8 => {
+            let shuffle = if input.bool() {
+                Shuffle::Lcg
+            } else {
+                Shuffle::StdRng
+            };
+            Box::new(WeirdPermute::new(input.u64()).with_shuffle(shuffle))
+        }
+        9 => Box::new(SeededPermute::new(input.u64())),
+        10 => Box::new(Thermalize::new(input.bath(), input.u64())),
+        11 => Box::new(CyclicShift {
+            subsystem: input.bath(),
+            offset: input.below(100) as isize - 50,
+        }),
+        12 => Box::new(ReflectBath {
+            subsystem: input.bath(),
+        }),
+        13 => {
+            let block_size = 1 + input.below(3);
+            let table = input.permutation(1 << block_size);
+            Box::new(BlockRule::new(input.bath(), block_size, table).ok()?)
+        }
+        14 | 15 => {
+            // Conditional is only reversible when `inner` can't change the
+            // predicate, so gate a hot-internal shuffle on the hot energy.
+            let parity = input.bool();
+            let inner: Box<dyn Rule> = match input.below(3) {
+                0 => Box::new(ReflectBath {
+                    subsystem: BathId::Hot,
+                }),
+                1 => Box::new(Thermalize::new(BathId::Hot, input.u64())),
+                _ => Box::new(CyclicShift {
+                    subsystem: BathId::Hot,
+                    offset: input.below(10) as isize,
+                }),
+            };
+            Box::new(Conditional {
+                predicate: Rc::new(move |w: &World| (w.hot_bath_energy() % 2 == 0) == parity),
+                inner,
+            })
+        }
+        16 => {
+            let (a, b) = (input.any_cell(world), input.any_cell(world));
+            let probability = input.byte() as f64 / 255.0;
+            Box::new(StochasticSwap::new(a, b, probability, input.u64()))
+        }
+        // An invalid pair of baths is turned away by `Simulation::try_new`.
+        17 => Box::new(OddEvenStep::new(input.bath(), input.bath())),
+        18 => Box::new(Then {
+            first: rule(input, world, depth - 1)?,
+            second: rule(input, world, depth - 1)?,
+        }),
+        19 => Box::new(Repeat {
+            inner: rule(input, world, depth - 1)?,
+            times: input.below(4),
+        }),
+        20 => {
+            let mut segments = Vec::new();
+            for _ in 0..1 + input.below(3) {
+                let start = input.below(200) as i64 - 100;
+                let end = start + input.below(50) as i64;
+                segments.push((start..end, rule(input, world, depth - 1)?));
+            }
+            Box::new(Scheduled::new(segments))
+        }
+        _ => {
+            let rules: Option<Vec<Box<dyn Rule>>> = (0..input.below(4))
+                .map(|_| rule(input, world, depth - 1))
+                .collect();
+            Box::new(rules?)
+        }
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut input = Input(data);
+    let start = world(&mut input);
+    let rules: Option<Vec<Box<dyn Rule>>> = (0..1 + input.below(6))
+        .map(|_| rule(&mut input, &start, 2))
+        .collect();
+    let Some(rules) = rules else {
+        return;
+    };
+    let Ok(mut sim) = Simulation::try_new(start.clone(), rules) else {
+        return;
+    };
+    let steps = input.below(64) as u64;
+    sim.step_n(steps);
+    for _ in 0..steps {
+        sim.step_back();
+    }
+    assert_eq!(sim.world(), &start, "not recovered after {steps} steps");
+});
@@ -0,0 +1,376 @@
+use std::{
+    cell::RefCell,
+    fmt::Debug,
+    ops::{Index, Range},
+};
+
+const WORD_BITS: usize = u64::BITS as usize;
+
+thread_local! {
+    static SCRATCH: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A fixed-length sequence of bits packed into `u64` words.
+///
+/// Bits past `len` in the last word are always kept clear, so equality and
+/// `count_ones` can work a word at a time.
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct BitBath {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl BitBath {
+    /// `len` cells, all empty.
+    pub fn new(len: usize) -> BitBath {
+        BitBath {
+            words: vec![0; len.div_ceil(WORD_BITS)],
+            len,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, i: usize) -> bool {
+        self.check(i);
+        self.words[i / WORD_BITS] >> (i % WORD_BITS) & 1 == 1
+    }
+
+    pub fn set(&mut self, i: usize, value: bool) {
+        self.check(i);
+        let mask = 1 << (i % WORD_BITS);
+        if value {
+            self.words[i / WORD_BITS] |= mask;
+        } else {
+            self.words[i / WORD_BITS] &= !mask;
+        }
+    }
+
+    /// Swaps cells `i` and `j` of this bath.
+    pub fn swap(&mut self, i: usize, j: usize) {
+        let (a, b) = (self.get(i), self.get(j));
+        self.set(i, b);
+        self.set(j, a);
+    }
+
+    /// Swaps cell `i` of this bath with cell `j` of `other`.
+    pub fn swap_with(&mut self, i: usize, other: &mut BitBath, j: usize) {
+        let (a, b) = (self.get(i), other.get(j));
+        self.set(i, b);
+        other.set(j, a);
+    }
+
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// How many cells in `range` are occupied. Panics if `range` runs past
+    /// the end or starts after it ends.
+    pub fn count_range(&self, range: Range<usize>) -> usize {
+        assert!(
+            range.start <= range.end && range.end <= self.len,
+            "range {range:?} out of bounds for length {}",
+            self.len
+        );
+        if range.is_empty() {
+            return 0;
+        }
+        let (first, last) = (range.start / WORD_BITS, (range.end - 1) / WORD_BITS);
+        (first..=last)
+            .map(|w| {
+                let mut word = self.words[w];
+                if w == first {
+                    word &= !0 << (range.start % WORD_BITS);
+                }
+                let end = range.end - w * WORD_BITS;
+                if end < WORD_BITS {
+                    word &= (1 << end) - 1;
+                }
+                word.count_ones() as usize
+            })
+            .sum()
+    }
+
+    /// How many neighbouring pairs of cells `(i, i + 1)` differ, i.e. the
+    /// number of boundaries between occupied and empty runs.
+    pub fn domain_walls(&self) -> usize {
+        let pairs = self.len.saturating_sub(1);
+        (0..self.words.len())
+            .map(|w| {
+                // Bit `i` of `next` is cell `i + 1` of the bath.
+                let carry = self
+                    .words
+                    .get(w + 1)
+                    .map_or(0, |&next| next << (WORD_BITS - 1));
+                let mut walls = self.words[w] ^ (self.words[w] >> 1 | carry);
+                let end = pairs.saturating_sub(w * WORD_BITS);
+                if end < WORD_BITS {
+                    walls &= (1 << end) - 1;
+                }
+                walls.count_ones() as usize
+            })
+            .sum()
+    }
+
+    /// How many cells differ between two baths of the same length.
+    pub fn hamming_distance(&self, other: &BitBath) -> usize {
+        assert_eq!(self.len, other.len, "bath length mismatch");
+        self.words
+            .iter()
+            .zip(&other.words)
+            .map(|(a, b)| (a ^ b).count_ones() as usize)
+            .sum()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(|i| self.get(i))
+    }
+
+    pub fn to_vec(&self) -> Vec<bool> {
+        self.iter().collect()
+    }
+
+    /// Same semantics as [`crate::permute`]: cell `i` receives the old
+    /// contents of cell `permutation[i]`.
+    pub fn permute(&mut self, permutation: &[usize]) {
+        assert_eq!(permutation.len(), self.len, "permutation length mismatch");
+        self.rebuild(|old, words| {
+            for (word, chunk) in words.iter_mut().zip(permutation.chunks(WORD_BITS)) {
+                for (i, &src) in chunk.iter().enumerate() {
+                    debug_assert!(src < permutation.len());
+                    *word |= (old[src / WORD_BITS] >> (src % WORD_BITS) & 1) << i;
+                }
+            }
+        });
+    }
+
+    /// Undoes `permute(permutation)`: cell `permutation[i]` receives the old
+    /// contents of cell `i`. Same as permuting by
+    /// [`crate::invert_permutation`], without building the inverse.
+    pub fn permute_inverse(&mut self, permutation: &[usize]) {
+        assert_eq!(permutation.len(), self.len, "permutation length mismatch");
+        self.rebuild(|old, words| {
+            for (i, &dst) in permutation.iter().enumerate() {
+                debug_assert!(dst < permutation.len());
+                words[dst / WORD_BITS] |=
+                    (old[i / WORD_BITS] >> (i % WORD_BITS) & 1) << (dst % WORD_BITS);
+            }
+        });
+    }
+
+    /// Replaces the words with ones `fill` writes, given the old words and
+    /// a zeroed buffer of the same length. The buffer is a per-thread
+    /// scratch vector that's swapped with the old words afterward, so
+    /// repeated calls don't allocate.
+    fn rebuild(&mut self, fill: impl FnOnce(&[u64], &mut [u64])) {
+        SCRATCH.with_borrow_mut(|scratch| {
+            scratch.clear();
+            scratch.resize(self.words.len(), 0);
+            fill(&self.words, scratch);
+            std::mem::swap(&mut self.words, scratch);
+        });
+    }
+
+    /// Moves the contents of each cell `i` to cell `(i + k) % len`.
+    pub fn rotate_right(&mut self, k: usize) {
+        if self.len == 0 {
+            return;
+        }
+        let k = k % self.len;
+        let rotated: BitBath = (0..self.len)
+            .map(|i| self.get((i + self.len - k) % self.len))
+            .collect();
+        *self = rotated;
+    }
+
+    /// Reverses the order of the cells.
+    pub fn reverse(&mut self) {
+        let reversed: BitBath = (0..self.len).rev().map(|i| self.get(i)).collect();
+        *self = reversed;
+    }
+
+    fn check(&self, i: usize) {
+        assert!(
+            i < self.len,
+            "index {i} out of range for bath of length {}",
+            self.len
+        );
+    }
+}
+
+impl Debug for BitBath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cells: String = self.iter().map(|b| if b { '#' } else { '.' }).collect();
+        f.debug_tuple("BitBath").field(&cells).finish()
+    }
+}
+
+impl Index<usize> for BitBath {
+    type Output = bool;
+
+    fn index(&self, i: usize) -> &bool {
+        if self.get(i) {
+            &true
+        } else {
+            &false
+        }
+    }
+}
+
+impl FromIterator<bool> for BitBath {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut bath = BitBath::default();
+        for b in iter {
+            if bath.len % WORD_BITS == 0 {
+                bath.words.push(0);
+            }
+            bath.len += 1;
+            bath.set(bath.len - 1, b);
+        }
+        bath
+    }
+}
+
+impl From<&[bool]> for BitBath {
+    fn from(bits: &[bool]) -> Self {
+        bits.iter().copied().collect()
+    }
+}
+
+impl From<Vec<bool>> for BitBath {
+    fn from(bits: Vec<bool>) -> Self {
+        bits.into_iter().collect()
+    }
+}
+
+impl<const N: usize> From<[bool; N]> for BitBath {
+    fn from(bits: [bool; N]) -> Self {
+        bits.into_iter().collect()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for BitBath {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for BitBath {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<bool>::deserialize(deserializer).map(BitBath::from)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_count_range() {
+        let bath: BitBath = (0..200).map(|i| i % 3 == 0).collect();
+        for (start, end) in [
+            (0, 200),
+            (0, 0),
+            (5, 5),
+            (1, 64),
+            (63, 65),
+            (64, 128),
+            (7, 190),
+        ] {
+            let expected = (start..end).filter(|i| i % 3 == 0).count();
+            assert_eq!(bath.count_range(start..end), expected, "{start}..{end}");
+        }
+    }
+
+    #[test]
+    fn test_domain_walls() {
+        for len in [0, 1, 2, 63, 64, 65, 130] {
+            for bath in [
+                (0..len).map(|i| i % 3 == 0).collect::<BitBath>(),
+                (0..len).map(|i| i < 64).collect(),
+                (0..len).map(|i| i % 2 == 1).collect(),
+            ] {
+                let expected = (1..len).filter(|&i| bath.get(i) != bath.get(i - 1)).count();
+                assert_eq!(bath.domain_walls(), expected, "{bath:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_hamming_distance() {
+        let a: BitBath = (0..130).map(|i| i % 3 == 0).collect();
+        let mut b = a.clone();
+        assert_eq!(a.hamming_distance(&b), 0);
+        b.set(1, true);
+        b.set(129, !b.get(129));
+        assert_eq!(a.hamming_distance(&b), 2);
+    }
+
+    #[test]
+    fn test_get_set() {
+        let mut bath = BitBath::new(130);
+        bath.set(0, true);
+        bath.set(64, true);
+        bath.set(129, true);
+        bath.set(64, false);
+        assert!(bath.get(0) && !bath.get(64) && bath.get(129));
+        assert_eq!(bath.count_ones(), 2);
+    }
+
+    #[test]
+    fn test_swap_with() {
+        let mut a = BitBath::from([true, false]);
+        let mut b = BitBath::from([false, false, false]);
+        a.swap_with(0, &mut b, 2);
+        assert_eq!(a.to_vec(), vec![false, false]);
+        assert_eq!(b.to_vec(), vec![false, false, true]);
+    }
+
+    #[test]
+    fn test_permute_matches_vec() {
+        let bits: Vec<bool> = (0..150).map(|i| i % 3 == 0 || i % 7 == 0).collect();
+        let permutation = crate::generate_random_permutation(bits.len(), 4);
+        let mut expected = bits.clone();
+        crate::permute(&permutation, &mut expected);
+        let mut bath = BitBath::from(bits);
+        bath.permute(&permutation);
+        assert_eq!(bath.to_vec(), expected);
+    }
+
+    #[test]
+    fn test_permute_inverse() {
+        let bits: Vec<bool> = (0..150).map(|i| i % 3 == 0 || i % 7 == 0).collect();
+        let permutation = crate::generate_random_permutation(bits.len(), 4);
+        let mut bath = BitBath::from(bits.clone());
+        bath.permute_inverse(&permutation);
+        let mut expected = BitBath::from(bits.clone());
+        expected.permute(&crate::invert_permutation(&permutation));
+        assert_eq!(bath, expected);
+        bath.permute(&permutation);
+        assert_eq!(bath, BitBath::from(bits));
+    }
+
+    #[test]
+    fn test_rotate_right() {
+        let mut bath = BitBath::from([true, true, false, false, false]);
+        bath.rotate_right(2);
+        assert_eq!(bath, BitBath::from([false, false, true, true, false]));
+        bath.rotate_right(9);
+        assert_eq!(bath, BitBath::from([false, true, true, false, false]));
+        BitBath::new(0).rotate_right(3);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_out_of_range() {
+        BitBath::new(3).get(3);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::{BathId, Simulation, World};
+
+/// Measures the energy current across a cut in one bath, between cells
+/// `cut - 1` and `cut`, as a simulation runs.
+///
+/// Rules can move a bit anywhere, so "crossing" isn't tracked per bit.
+/// Instead a step's current is how much the occupied count of cells
+/// `0..cut` fell: positive when energy flowed right, out of the left part,
+/// and negative when it flowed left into it. On a cyclic bath, energy
+/// wrapping from the last cell to the first counts as flowing left. Energy
+/// exchanged with other subsystems shows up too, if it enters or leaves
+/// the left part.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CurrentMeter {
+    pub id: BathId,
+    pub cut: usize,
+    last_step: i64,
+    total: i64,
+}
+
+impl CurrentMeter {
+    pub fn new(id: BathId, cut: usize) -> CurrentMeter {
+        CurrentMeter {
+            id,
+            cut,
+            last_step: 0,
+            total: 0,
+        }
+    }
+
+    fn left(&self, world: &World) -> i64 {
+        world
+            .count_range(self.id, 0..self.cut)
+            .unwrap_or_else(|e| panic!("{e}")) as i64
+    }
+
+    /// Steps `sim` once and records the current during that step. Panics if
+    /// the bath is missing or shorter than `cut`.
+    pub fn step(&mut self, sim: &mut Simulation) {
+        let before = self.left(sim.world());
+        sim.step();
+        self.last_step = before - self.left(sim.world());
+        self.total += self.last_step;
+    }
+
+    pub fn step_n(&mut self, sim: &mut Simulation, n: u64) {
+        for _ in 0..n {
+            self.step(sim);
+        }
+    }
+
+    /// The current during the most recent step.
+    pub fn last_step(&self) -> i64 {
+        self.last_step
+    }
+
+    /// The net current over every recorded step.
+    pub fn total(&self) -> i64 {
+        self.total
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::CyclicShift;
+
+    fn sim(hot: [bool; 10], offset: isize) -> Simulation {
+        let world = World::builder().hot_bath(hot).build();
+        let rules = vec![Box::new(CyclicShift {
+            subsystem: BathId::Hot,
+            offset,
+        }) as Box<_>];
+        Simulation::new(world, rules)
+    }
+
+    #[test]
+    fn test_single_bit_shift() {
+        let mut hot = [false; 10];
+        hot[0] = true;
+        let mut sim = sim(hot, 1);
+        let mut meter = CurrentMeter::new(BathId::Hot, 5);
+        let currents: Vec<i64> = (0..10)
+            .map(|_| {
+                meter.step(&mut sim);
+                meter.last_step()
+            })
+            .collect();
+        // The bit crosses the cut on the fifth step and wraps back to cell 0
+        // on the tenth.
+        assert_eq!(currents, [0, 0, 0, 0, 1, 0, 0, 0, 0, -1]);
+        assert_eq!(meter.total(), 0);
+    }
+
+    #[test]
+    fn test_leftward_block() {
+        let hot = [
+            false, false, false, false, false, false, true, true, true, false,
+        ];
+        let mut sim = sim(hot, -2);
+        let mut meter = CurrentMeter::new(BathId::Hot, 5);
+        meter.step_n(&mut sim, 2);
+        // The bits in 6..9 move to 4..7, then to 2..5: one crosses into the
+        // left part on the first step and the other two on the second.
+        assert_eq!(meter.total(), -3);
+        assert_eq!(meter.last_step(), -2);
+    }
+}
@@ -0,0 +1,154 @@
+use rand::RngCore;
+
+use crate::{check_fills, thermal_bath, BathId, Rule, SimConfig, World};
+
+/// Runs `n_cycles` engine cycles on `World::thermal(config, rng)` and
+/// returns the battery energy gained during each one (negative if it lost
+/// energy).
+///
+/// A cycle is a hot phase of `config.n_steps` steps of `hot_rules`, then a
+/// cold phase of as many steps of `cold_rules`. Before each phase the bath
+/// it couples to is swapped for a fresh reservoir in a new thermal state,
+/// drawn from `rng` as `World::thermal` draws it, so no two phases are
+/// likely to start from the same layout; the battery and the other bath
+/// carry over.
+///
+/// Panics if either fill is outside `[0, 1]`.
+pub fn run_cycles<R: RngCore>(
+    config: &SimConfig,
+    hot_rules: &dyn Rule,
+    cold_rules: &dyn Rule,
+    n_cycles: usize,
+    rng: &mut R,
+) -> Vec<i64> {
+    check_fills(config);
+    let mut world = World::thermal(config, rng);
+    let mut phase = |world: &mut World, rules: &dyn Rule, bath: BathId, fill: f64| {
+        *world.subsystem_mut(bath) = thermal_bath(config.bath_size, fill, rng);
+        for _ in 0..config.n_steps {
+            rules.step(world);
+            world.t += 1;
+        }
+    };
+    (0..n_cycles)
+        .map(|_| {
+            let before = world.battery_energy() as i64;
+            phase(&mut world, hot_rules, BathId::Hot, config.hot_fill);
+            phase(&mut world, cold_rules, BathId::Cold, config.cold_fill);
+            world.battery_energy() as i64 - before
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+    use crate::{BitBath, NoOp};
+
+    /// Moves a quantum from `from` into the first empty cell of `to`, if
+    /// `to` holds fewer than `limit`. Not reversible; only for tests.
+    #[derive(Clone)]
+    struct Transfer {
+        from: BathId,
+        to: BathId,
+        limit: usize,
+    }
+
+    impl Rule for Transfer {
+        fn step(&self, world: &mut World) {
+            if world.energy(self.to) >= self.limit {
+                return;
+            }
+            let source = world.subsystem(self.from).iter().position(|b| b);
+            let sink = world.subsystem(self.to).iter().position(|b| !b);
+            if let (Some(i), Some(j)) = (source, sink) {
+                world.swap_cells((self.from, i), (self.to, j));
+            }
+        }
+
+        fn inverse(&self) -> Box<dyn Rule> {
+            Box::new(NoOp)
+        }
+
+        fn clone_box(&self) -> Box<dyn Rule> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn test_scripted_cycle() {
+        let config = SimConfig {
+            battery_size: 10,
+            bath_size: 8,
+            n_steps: 3,
+            hot_fill: 1.0,
+            ..SimConfig::default()
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+        // Each hot phase charges three quanta from the refilled hot bath;
+        // each cold phase dumps one into the emptied cold bath. The last
+        // cycle's hot phase runs out of battery room after two.
+        let charge = Transfer {
+            from: BathId::Hot,
+            to: BathId::Battery,
+            limit: usize::MAX,
+        };
+        let dump = Transfer {
+            from: BathId::Battery,
+            to: BathId::Cold,
+            limit: 1,
+        };
+        assert_eq!(
+            run_cycles(&config, &charge, &dump, 5, &mut rng),
+            vec![2, 2, 2, 2, 1]
+        );
+        assert_eq!(run_cycles(&config, &NoOp, &NoOp, 2, &mut rng), vec![0, 0]);
+    }
+
+    /// Remembers the hot bath it sees each step.
+    #[derive(Clone, Default)]
+    struct RecordHot(Rc<RefCell<Vec<BitBath>>>);
+
+    impl Rule for RecordHot {
+        fn step(&self, world: &mut World) {
+            self.0.borrow_mut().push(world.hot_bath().clone());
+        }
+
+        fn inverse(&self) -> Box<dyn Rule> {
+            Box::new(NoOp)
+        }
+
+        fn clone_box(&self) -> Box<dyn Rule> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn test_fresh_baths() {
+        let config = SimConfig {
+            battery_size: 2,
+            bath_size: 40,
+            n_steps: 1,
+            ..SimConfig::default()
+        };
+        let record = RecordHot::default();
+        let mut rng = StdRng::seed_from_u64(3);
+        run_cycles(&config, &record, &NoOp, 10, &mut rng);
+        let seen = record.0.borrow();
+        assert_eq!(seen.len(), 10);
+        for (i, a) in seen.iter().enumerate() {
+            for b in &seen[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+        // Roughly half full, but not laid out as a block.
+        for bath in seen.iter() {
+            assert!((10..=30).contains(&bath.count_ones()));
+            assert_ne!(bath.domain_walls(), 1);
+        }
+    }
+}
@@ -0,0 +1,193 @@
+use std::fmt::Display;
+
+use crate::{BathId, BitBath, World};
+
+/// Where two worlds disagree; see `World::diff`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WorldDiff {
+    /// Both worlds' `t`, if they differ.
+    pub t: Option<(i64, i64)>,
+    /// For each subsystem with any differences, the indices of the cells
+    /// that differ. A cell only one world has counts as differing.
+    pub cells: Vec<(BathId, Vec<usize>)>,
+}
+
+impl WorldDiff {
+    pub fn is_empty(&self) -> bool {
+        self.t.is_none() && self.cells.is_empty()
+    }
+}
+
+impl World {
+    pub fn diff(&self, other: &World) -> WorldDiff {
+        let n = self.subsystems().len().max(other.subsystems().len());
+        let empty = BitBath::new(0);
+        let cells = (0..n)
+            .filter_map(|s| {
+                let a = self.subsystems().get(s).map_or(&empty, |x| &x.cells);
+                let b = other.subsystems().get(s).map_or(&empty, |x| &x.cells);
+                let indices: Vec<usize> = (0..a.len().max(b.len()))
+                    .filter(|&i| i >= a.len() || i >= b.len() || a.get(i) != b.get(i))
+                    .collect();
+                (!indices.is_empty()).then_some((BathId::from_index(s), indices))
+            })
+            .collect();
+        WorldDiff {
+            t: (self.t != other.t).then_some((self.t, other.t)),
+            cells,
+        }
+    }
+}
+
+/// Why two worlds can't be compared cell by cell.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ShapeError {
+    SubsystemCount {
+        a: usize,
+        b: usize,
+    },
+    Length {
+        subsystem: BathId,
+        a: usize,
+        b: usize,
+    },
+}
+
+impl Display for ShapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShapeError::SubsystemCount { a, b } => {
+                write!(f, "worlds have {a} and {b} subsystems")
+            }
+            ShapeError::Length { subsystem, a, b } => {
+                write!(
+                    f,
+                    "{subsystem} has {a} cells in one world and {b} in the other"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ShapeError {}
+
+/// The total number of cells that differ between `a` and `b`, over every
+/// subsystem. `t` isn't compared. Unlike `World::diff`, the worlds must
+/// have the same subsystems with the same lengths.
+pub fn occupation_distance(a: &World, b: &World) -> Result<usize, ShapeError> {
+    if a.subsystems().len() != b.subsystems().len() {
+        return Err(ShapeError::SubsystemCount {
+            a: a.subsystems().len(),
+            b: b.subsystems().len(),
+        });
+    }
+    let mut distance = 0;
+    for (id, (x, y)) in a.ids().zip(a.subsystems().iter().zip(b.subsystems())) {
+        if x.cells.len() != y.cells.len() {
+            return Err(ShapeError::Length {
+                subsystem: id,
+                a: x.cells.len(),
+                b: y.cells.len(),
+            });
+        }
+        distance += x.cells.hamming_distance(&y.cells);
+    }
+    Ok(distance)
+}
+
+/// One line for `t` if it differs, then one line per differing subsystem,
+/// e.g. `hot: 3, 17`. An empty diff prints `no differences`.
+impl Display for WorldDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no differences");
+        }
+        let mut lines = Vec::new();
+        if let Some((a, b)) = self.t {
+            lines.push(format!("t: {a} vs {b}"));
+        }
+        for (id, indices) in &self.cells {
+            let indices: Vec<String> = indices.iter().map(|i| i.to_string()).collect();
+            lines.push(format!("{id}: {}", indices.join(", ")));
+        }
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_occupation_distance() {
+        let a = World::builder()
+            .battery([true, false])
+            .hot_bath_half_full(9)
+            .cold_bath([false; 4])
+            .build();
+        let mut b = a.clone();
+        b.t = 5;
+        assert_eq!(occupation_distance(&a, &b), Ok(0));
+        b.subsystem_mut(BathId::Hot).set(7, true);
+        assert_eq!(occupation_distance(&a, &b), Ok(1));
+        b.subsystem_mut(BathId::Battery).set(0, false);
+        assert_eq!(occupation_distance(&b, &a), Ok(2));
+    }
+
+    #[test]
+    fn test_occupation_distance_shape() {
+        let a = World::new(2, 4);
+        assert_eq!(
+            occupation_distance(&a, &World::new(2, 5)),
+            Err(ShapeError::Length {
+                subsystem: BathId::Hot,
+                a: 4,
+                b: 5,
+            })
+        );
+        let mut b = a.clone();
+        b.add_subsystem("warm", [false]);
+        assert_eq!(
+            occupation_distance(&a, &b).unwrap_err().to_string(),
+            "worlds have 3 and 4 subsystems"
+        );
+    }
+
+    #[test]
+    fn test_mismatched() {
+        let a = World::builder()
+            .t(3)
+            .battery([true, false])
+            .hot_bath([true, true, false, false])
+            .cold_bath([false])
+            .build();
+        let b = World::builder()
+            .t(4)
+            .battery([true, false])
+            .hot_bath([false, true, false, true])
+            .cold_bath([false, true])
+            .extra("warm", [false])
+            .build();
+        let diff = a.diff(&b);
+        assert_eq!(
+            diff,
+            WorldDiff {
+                t: Some((3, 4)),
+                cells: vec![
+                    (BathId::Hot, vec![0, 3]),
+                    (BathId::Cold, vec![1]),
+                    (BathId::Extra(0), vec![0]),
+                ],
+            }
+        );
+        assert_eq!(diff.to_string(), "t: 3 vs 4\nhot: 0, 3\ncold: 1\nextra0: 0");
+    }
+
+    #[test]
+    fn test_identical() {
+        let world = World::new(4, 10);
+        let diff = world.diff(&world.clone());
+        assert!(diff.is_empty());
+        assert_eq!(diff.to_string(), "no differences");
+    }
+}
@@ -0,0 +1,116 @@
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+use crate::{SimConfig, Simulation, World};
+
+/// Runs the default rule set for `config.n_steps` once per seed and returns
+/// `observe` of each final world, in the same order as `seeds`.
+///
+/// With the `rayon` feature the runs happen in parallel.
+pub fn run_ensemble(
+    config: &SimConfig,
+    seeds: &[u64],
+    observe: impl Fn(&World) -> f64 + Sync,
+) -> Vec<f64> {
+    let run = |&seed: &u64| {
+        let mut sim = Simulation::seeded(config, seed);
+        sim.step_n(config.n_steps);
+        observe(sim.world())
+    };
+    #[cfg(feature = "rayon")]
+    return seeds.par_iter().map(run).collect();
+    #[cfg(not(feature = "rayon"))]
+    return seeds.iter().map(run).collect();
+}
+
+/// The mean of some samples and its standard error.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stats {
+    pub mean: f64,
+    /// The sample standard deviation over `sqrt(n)`; NaN with fewer than two
+    /// samples.
+    pub stderr: f64,
+}
+
+impl Stats {
+    pub fn from_samples(samples: &[f64]) -> Stats {
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = samples.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        Stats {
+            mean,
+            stderr: if samples.len() < 2 {
+                f64::NAN
+            } else {
+                (variance / n).sqrt()
+            },
+        }
+    }
+}
+
+/// How much `World::total_entropy` grows over `steps` steps from
+/// `World::from_config(config)`, averaged over one run per seed.
+pub fn mean_entropy_production(config: &SimConfig, seeds: &[u64], steps: u64) -> Stats {
+    let config = SimConfig {
+        n_steps: steps,
+        ..config.clone()
+    };
+    let start = World::from_config(&config).total_entropy();
+    let deltas = run_ensemble(&config, seeds, |w| w.total_entropy() - start);
+    Stats::from_samples(&deltas)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{default_rules, Rule};
+
+    #[test]
+    fn test_matches_serial() {
+        let config = SimConfig {
+            battery_size: 4,
+            bath_size: 20,
+            n_steps: 200,
+            ..SimConfig::default()
+        };
+        let seeds = [1, 2, 3, 4, 5, 6, 7, 8];
+        let observe = |w: &World| w.battery_energy() as f64;
+        let serial: Vec<f64> = seeds
+            .iter()
+            .map(|&seed| {
+                let mut world = World::from_config(&config);
+                let rules = default_rules(seed);
+                for _ in 0..config.n_steps {
+                    rules.step(&mut world);
+                    world.t += 1;
+                }
+                observe(&world)
+            })
+            .collect();
+        assert_eq!(run_ensemble(&config, &seeds, observe), serial);
+    }
+
+    #[test]
+    fn test_stats() {
+        let stats = Stats::from_samples(&[1.0, 2.0, 3.0, 6.0]);
+        assert_eq!(stats.mean, 3.0);
+        assert!((stats.stderr - (14.0f64 / 3.0 / 4.0).sqrt()).abs() < 1e-12);
+        assert!(Stats::from_samples(&[5.0]).stderr.is_nan());
+    }
+
+    #[test]
+    fn test_entropy_production() {
+        let config = SimConfig {
+            battery_size: 3,
+            bath_size: 12,
+            n_steps: 0,
+            ..SimConfig::default()
+        };
+        let seeds = [1, 2, 3, 4, 5];
+        // The cold bath starts empty, so mixing can only raise its entropy.
+        let stats = mean_entropy_production(&config, &seeds, 100);
+        assert!(stats.mean > 0.0);
+        assert!(stats.stderr.is_finite());
+        assert_eq!(mean_entropy_production(&config, &seeds, 0).mean, 0.0);
+    }
+}
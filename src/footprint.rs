@@ -0,0 +1,166 @@
+use std::collections::BTreeSet;
+
+use crate::{check_cell, check_subsystem, BathId, RuleError, World};
+
+/// The cells a rule reads or writes, as far as it can say without seeing a
+/// world: some individual cells, some whole subsystems, or everything.
+///
+/// It's best-effort. Rules that move whole baths around report the bath
+/// rather than listing cells, and a rule that doesn't override
+/// `Rule::footprint` reports nothing at all.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Footprint {
+    cells: BTreeSet<(BathId, usize)>,
+    subsystems: BTreeSet<BathId>,
+    all: bool,
+}
+
+impl Footprint {
+    /// Touches nothing.
+    pub fn new() -> Footprint {
+        Footprint::default()
+    }
+
+    /// Touches every cell of every subsystem, however many there are.
+    pub fn all() -> Footprint {
+        Footprint {
+            all: true,
+            ..Footprint::default()
+        }
+    }
+
+    pub fn from_cells(cells: impl IntoIterator<Item = (BathId, usize)>) -> Footprint {
+        Footprint {
+            cells: cells.into_iter().collect(),
+            ..Footprint::default()
+        }
+    }
+
+    pub fn from_subsystems(subsystems: impl IntoIterator<Item = BathId>) -> Footprint {
+        Footprint {
+            subsystems: subsystems.into_iter().collect(),
+            ..Footprint::default()
+        }
+    }
+
+    /// Everything either footprint touches.
+    pub fn union(mut self, other: &Footprint) -> Footprint {
+        self.cells.extend(&other.cells);
+        self.subsystems.extend(&other.subsystems);
+        self.all |= other.all;
+        self
+    }
+
+    /// The individually listed cells, not counting whole subsystems.
+    pub fn cells(&self) -> impl Iterator<Item = (BathId, usize)> + '_ {
+        self.cells.iter().copied()
+    }
+
+    /// The subsystems touched as a whole.
+    pub fn subsystems(&self) -> impl Iterator<Item = BathId> + '_ {
+        self.subsystems.iter().copied()
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.all
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.all && self.cells.is_empty() && self.subsystems.is_empty()
+    }
+
+    pub fn contains(&self, (subsystem, index): (BathId, usize)) -> bool {
+        self.all || self.subsystems.contains(&subsystem) || self.cells.contains(&(subsystem, index))
+    }
+
+    /// Every subsystem of `world` the footprint touches any part of.
+    pub fn baths(&self, world: &World) -> BTreeSet<BathId> {
+        if self.all {
+            return (0..world.subsystems().len())
+                .map(BathId::from_index)
+                .collect();
+        }
+        let mut baths = self.subsystems.clone();
+        baths.extend(self.cells.iter().map(|&(id, _)| id));
+        baths
+    }
+
+    /// Checks that every listed subsystem and cell exists in `world`,
+    /// reporting the first that doesn't as an error from `rule`.
+    pub fn check(&self, rule: &'static str, world: &World) -> Result<(), RuleError> {
+        for &subsystem in &self.subsystems {
+            check_subsystem(rule, world, subsystem)?;
+        }
+        for &cell in &self.cells {
+            check_cell(rule, world, cell)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_contains() {
+        let footprint = Footprint::from_cells([(BathId::Battery, 2)])
+            .union(&Footprint::from_subsystems([BathId::Cold]));
+        assert!(footprint.contains((BathId::Battery, 2)));
+        assert!(!footprint.contains((BathId::Battery, 1)));
+        assert!(footprint.contains((BathId::Cold, 100)));
+        assert!(!footprint.contains((BathId::Hot, 0)));
+        assert!(Footprint::all().contains((BathId::Extra(3), 7)));
+        assert!(!Footprint::new().contains((BathId::Hot, 0)));
+    }
+
+    #[test]
+    fn test_is_empty() {
+        assert!(Footprint::new().is_empty());
+        assert!(!Footprint::all().is_empty());
+        assert!(!Footprint::from_subsystems([BathId::Hot]).is_empty());
+        assert!(Footprint::new().union(&Footprint::new()).is_empty());
+    }
+
+    #[test]
+    fn test_baths() {
+        let world = World::builder().extra("x", [true]).build();
+        let footprint = Footprint::from_cells([(BathId::Hot, 3), (BathId::Hot, 4)])
+            .union(&Footprint::from_subsystems([BathId::Cold]));
+        assert_eq!(
+            footprint.baths(&world).into_iter().collect::<Vec<_>>(),
+            [BathId::Hot, BathId::Cold]
+        );
+        assert_eq!(Footprint::all().baths(&world).len(), 4);
+        assert!(Footprint::new().baths(&world).is_empty());
+    }
+
+    #[test]
+    fn test_check() {
+        let world = World::builder()
+            .battery([true])
+            .hot_bath([false, true])
+            .build();
+        let footprint = Footprint::from_cells([(BathId::Hot, 1), (BathId::Battery, 0)]);
+        assert_eq!(footprint.check("Test", &world), Ok(()));
+        assert_eq!(
+            footprint
+                .union(&Footprint::from_cells([(BathId::Hot, 2)]))
+                .check("Test", &world),
+            Err(RuleError::OutOfRange {
+                rule: "Test",
+                subsystem: BathId::Hot,
+                index: 2,
+                len: 2,
+            })
+        );
+        assert_eq!(
+            Footprint::from_subsystems([BathId::Extra(0)]).check("Test", &world),
+            Err(RuleError::MissingSubsystem {
+                rule: "Test",
+                subsystem: BathId::Extra(0),
+            })
+        );
+        assert_eq!(Footprint::all().check("Test", &world), Ok(()));
+    }
+}
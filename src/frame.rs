@@ -0,0 +1,125 @@
+use std::path::Path;
+
+use image::{ImageResult, Rgb, RgbImage};
+
+use crate::{BathId, Footprint, World};
+
+const OCCUPIED: Rgb<u8> = Rgb([32, 32, 32]);
+const EMPTY: Rgb<u8> = Rgb([240, 240, 240]);
+/// Cells in the highlighted footprint, occupied and empty.
+const ACTIVE_OCCUPIED: Rgb<u8> = Rgb([160, 32, 32]);
+const ACTIVE_EMPTY: Rgb<u8> = Rgb([240, 184, 184]);
+/// Fills the space past the end of baths shorter than the longest one.
+const BACKGROUND: Rgb<u8> = Rgb([160, 160, 160]);
+
+/// Draws each subsystem as a row of `cell`-pixel squares, dark for occupied
+/// cells and light for empty ones. The image is as wide as the longest
+/// subsystem.
+pub fn render_frame(world: &World, cell: u32) -> RgbImage {
+    render_frame_highlighting(world, cell, &Footprint::new())
+}
+
+/// `render_frame`, with the cells in `footprint` (say, a rule's) tinted red.
+pub fn render_frame_highlighting(world: &World, cell: u32, footprint: &Footprint) -> RgbImage {
+    let subsystems = world.subsystems();
+    let longest = subsystems.iter().map(|s| s.cells.len()).max().unwrap_or(0);
+    RgbImage::from_fn(
+        longest as u32 * cell,
+        subsystems.len() as u32 * cell,
+        |x, y| {
+            let row = (y / cell) as usize;
+            let bath = &subsystems[row].cells;
+            let i = (x / cell) as usize;
+            let active = footprint.contains((BathId::from_index(row), i));
+            if i >= bath.len() {
+                BACKGROUND
+            } else if bath.get(i) {
+                if active {
+                    ACTIVE_OCCUPIED
+                } else {
+                    OCCUPIED
+                }
+            } else if active {
+                ACTIVE_EMPTY
+            } else {
+                EMPTY
+            }
+        },
+    )
+}
+
+/// Renders each world and saves it to `dir/frame_00000.png`,
+/// `dir/frame_00001.png`, ... in order.
+pub fn save_frames<'a>(
+    worlds: impl IntoIterator<Item = &'a World>,
+    cell: u32,
+    dir: impl AsRef<Path>,
+) -> ImageResult<()> {
+    for (i, world) in worlds.into_iter().enumerate() {
+        render_frame(world, cell).save(dir.as_ref().join(format!("frame_{i:05}.png")))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn world() -> World {
+        World::builder()
+            .battery([true, false])
+            .hot_bath([false, true, true, false, true])
+            .cold_bath([false])
+            .build()
+    }
+
+    #[test]
+    fn test_dimensions() {
+        let image = render_frame(&world(), 4);
+        assert_eq!(image.dimensions(), (20, 12));
+        assert_eq!(render_frame(&World::default(), 4).dimensions(), (0, 12));
+    }
+
+    #[test]
+    fn test_colors() {
+        let image = render_frame(&world(), 3);
+        assert_eq!(image[(0, 0)], OCCUPIED);
+        assert_eq!(image[(5, 2)], EMPTY);
+        assert_eq!(image[(6, 0)], BACKGROUND);
+        assert_eq!(image[(4, 4)], OCCUPIED);
+        assert_eq!(image[(14, 8)], BACKGROUND);
+    }
+
+    #[test]
+    fn test_highlighting() {
+        let footprint = Footprint::from_cells([(BathId::Battery, 0), (BathId::Hot, 0)])
+            .union(&Footprint::from_subsystems([BathId::Cold]));
+        let image = render_frame_highlighting(&world(), 3, &footprint);
+        assert_eq!(image[(0, 0)], ACTIVE_OCCUPIED);
+        assert_eq!(image[(3, 0)], EMPTY);
+        assert_eq!(image[(0, 3)], ACTIVE_EMPTY);
+        assert_eq!(image[(3, 3)], OCCUPIED);
+        assert_eq!(image[(0, 6)], ACTIVE_EMPTY);
+        // Past the end of a bath stays background even in a highlighted one.
+        assert_eq!(image[(3, 6)], BACKGROUND);
+        assert_eq!(
+            render_frame_highlighting(&world(), 3, &Footprint::new()),
+            render_frame(&world(), 3)
+        );
+    }
+
+    #[test]
+    fn test_save_frames() {
+        let dir = std::env::temp_dir().join(format!(
+            "generalized-heat-engine-{}-frames",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut later = world();
+        later.t = 1;
+        save_frames([&world(), &later], 2, &dir).unwrap();
+        let loaded = image::open(dir.join("frame_00001.png")).unwrap().to_rgb8();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(loaded, render_frame(&later, 2));
+    }
+}
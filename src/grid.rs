@@ -0,0 +1,172 @@
+use std::{fmt::Display, str::FromStr};
+
+use crate::{BitBath, World};
+
+/// Something wrong with a world written out as a grid. `line` counts from 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GridError {
+    BadTime {
+        line: usize,
+        value: String,
+    },
+    BadCell {
+        line: usize,
+        column: usize,
+        found: char,
+    },
+    /// The line ends in `…`, as `{world:#}` prints baths longer than its
+    /// width.
+    Truncated {
+        line: usize,
+    },
+    /// There weren't exactly three subsystem lines.
+    WrongLineCount {
+        found: usize,
+    },
+}
+
+impl Display for GridError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GridError::BadTime { line, value } => {
+                write!(f, "line {line}: invalid time {value:?}")
+            }
+            GridError::BadCell {
+                line,
+                column,
+                found,
+            } => write!(
+                f,
+                "line {line}, column {column}: expected `#` or `.`, got {found:?}"
+            ),
+            GridError::Truncated { line } => write!(
+                f,
+                "line {line} is cut off; print the world with a precision at least its longest bath"
+            ),
+            GridError::WrongLineCount { found } => write!(
+                f,
+                "expected battery, hot, and cold lines, got {found} line(s)"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GridError {}
+
+/// Reads the classic three subsystems in the format `{world:#}` prints: an
+/// optional `t=<n>` line (`t` is 0 without it), then one line each for the
+/// battery, hot bath, and cold bath, with `#` for an occupied cell and `.`
+/// for an empty one. Trailing whitespace on a line is ignored, as is a
+/// final newline after the cold bath's line.
+///
+/// Pass a large enough precision when printing, e.g.
+/// `format!("{world:#.1000}")`, or long baths are cut off and won't parse.
+impl FromStr for World {
+    type Err = GridError;
+
+    fn from_str(s: &str) -> Result<World, GridError> {
+        let mut lines: Vec<(usize, &str)> = s
+            .split('\n')
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim_end()))
+            .collect();
+        let mut t = 0;
+        if let Some(&(line, first)) = lines.first() {
+            if let Some(value) = first.strip_prefix("t=") {
+                t = value.parse().map_err(|_| GridError::BadTime {
+                    line,
+                    value: value.to_string(),
+                })?;
+                lines.remove(0);
+            }
+        }
+        // Empty baths print as empty lines, so count lines with `split`
+        // rather than `lines` and only then drop a trailing newline.
+        if lines.len() == 4 && lines[3].1.is_empty() {
+            lines.pop();
+        }
+        let [battery, hot_bath, cold_bath] = lines[..] else {
+            return Err(GridError::WrongLineCount { found: lines.len() });
+        };
+        Ok(World::builder()
+            .t(t)
+            .battery(parse_bath(battery)?)
+            .hot_bath(parse_bath(hot_bath)?)
+            .cold_bath(parse_bath(cold_bath)?)
+            .build())
+    }
+}
+
+fn parse_bath((line, cells): (usize, &str)) -> Result<BitBath, GridError> {
+    if cells.ends_with('…') {
+        return Err(GridError::Truncated { line });
+    }
+    cells
+        .chars()
+        .enumerate()
+        .map(|(i, c)| match c {
+            '#' => Ok(true),
+            '.' => Ok(false),
+            found => Err(GridError::BadCell {
+                line,
+                column: i + 1,
+                found,
+            }),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let world = World::builder()
+            .t(-17)
+            .battery([true, false, false])
+            .hot_bath_half_full(130)
+            .cold_bath([false, true])
+            .build();
+        let printed = format!("{world:#.1000}");
+        assert_eq!(printed.parse::<World>(), Ok(world));
+
+        let empty = World::default();
+        assert_eq!(format!("{empty:#}").parse::<World>(), Ok(empty));
+    }
+
+    #[test]
+    fn test_without_time() {
+        assert_eq!(
+            "#.\r\n##.\n...\n".parse::<World>(),
+            Ok(World::classic(
+                [true, false],
+                [true, true, false],
+                [false; 3]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = |s: &str| s.parse::<World>().unwrap_err();
+        assert_eq!(
+            err("t=x\n#\n#\n#"),
+            GridError::BadTime {
+                line: 1,
+                value: "x".to_string(),
+            }
+        );
+        assert_eq!(
+            err("t=1\n#\n#o#\n#").to_string(),
+            "line 3, column 2: expected `#` or `.`, got 'o'"
+        );
+        assert_eq!(err("#\n#"), GridError::WrongLineCount { found: 2 });
+        assert_eq!(
+            err("t=0\n#\n#\n#\n#"),
+            GridError::WrongLineCount { found: 4 }
+        );
+        let long = World::new(1, 100);
+        assert_eq!(err(&format!("{long:#}")), GridError::Truncated { line: 3 });
+    }
+}
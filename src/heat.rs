@@ -0,0 +1,97 @@
+use crate::{BathId, World};
+
+/// Signed energy changes per subsystem, both for the latest step and summed
+/// over a run. Entry `i` of each slice is for `BathId::from_index(i)`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HeatLedger {
+    last_step: Vec<i64>,
+    totals: Vec<i64>,
+}
+
+pub(crate) fn energies(world: &World) -> Vec<usize> {
+    world.ids().map(|id| world.energy(id)).collect()
+}
+
+impl HeatLedger {
+    pub fn new() -> HeatLedger {
+        HeatLedger::default()
+    }
+
+    /// Records the change from `before` (as returned by `energies`) to
+    /// `after`.
+    pub(crate) fn record(&mut self, before: &[usize], after: &World) {
+        self.last_step = energies(after)
+            .iter()
+            .zip(before)
+            .map(|(&a, &b)| a as i64 - b as i64)
+            .collect();
+        if self.totals.len() < self.last_step.len() {
+            self.totals.resize(self.last_step.len(), 0);
+        }
+        for (total, delta) in self.totals.iter_mut().zip(&self.last_step) {
+            *total += delta;
+        }
+    }
+
+    pub fn last_step(&self) -> &[i64] {
+        &self.last_step
+    }
+
+    pub fn totals(&self) -> &[i64] {
+        &self.totals
+    }
+
+    /// Net energy `id` has gained so far; zero for baths never recorded.
+    pub fn total(&self, id: BathId) -> i64 {
+        self.totals.get(id.index()).copied().unwrap_or(0)
+    }
+
+    pub fn hot_released(&self) -> i64 {
+        -self.total(BathId::Hot)
+    }
+
+    pub fn cold_absorbed(&self) -> i64 {
+        self.total(BathId::Cold)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{CondSwap, Rule, Simulation, WeirdPermute, World};
+
+    #[test]
+    fn test_single_transfer() {
+        let world = World::builder()
+            .battery([false])
+            .hot_bath([true, true, false])
+            .cold_bath([false, false])
+            .build();
+        let mut sim = Simulation::new(world, vec![Box::new(CondSwap)]);
+        sim.step();
+        assert_eq!(sim.heat().last_step(), &[1, -2, 1]);
+        assert_eq!(sim.heat().hot_released(), 2);
+        assert_eq!(sim.heat().cold_absorbed(), 1);
+    }
+
+    #[test]
+    fn test_conservation() {
+        let world = World::builder()
+            .battery_empty(4)
+            .hot_bath_half_full(16)
+            .cold_bath_empty(16)
+            .build();
+        let rules: Vec<Box<dyn Rule>> = vec![Box::new(CondSwap), Box::new(WeirdPermute::new(4))];
+        let mut sim = Simulation::new(world, rules);
+        for _ in 0..200 {
+            sim.step();
+            assert_eq!(sim.heat().last_step().iter().sum::<i64>(), 0);
+        }
+        assert_eq!(sim.heat().totals().iter().sum::<i64>(), 0);
+        assert!(sim.heat().hot_released() > 0);
+        assert_eq!(
+            sim.heat().hot_released(),
+            sim.heat().cold_absorbed() + sim.work_extracted()
+        );
+    }
+}
@@ -0,0 +1,192 @@
+/// Counts of how often each value in `0..=max` has been seen. Values past
+/// `max` grow the histogram to fit.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Histogram {
+    counts: Vec<u64>,
+}
+
+impl Histogram {
+    pub fn new(max: usize) -> Histogram {
+        Histogram {
+            counts: vec![0; max + 1],
+        }
+    }
+
+    pub fn record(&mut self, value: usize) {
+        if value >= self.counts.len() {
+            self.counts.resize(value + 1, 0);
+        }
+        self.counts[value] += 1;
+    }
+
+    /// `counts()[v]` is how many times `v` was recorded.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// The counts divided by their total, or all zeros if nothing has been
+    /// recorded.
+    pub fn frequencies(&self) -> Vec<f64> {
+        let total = self.total().max(1) as f64;
+        self.counts.iter().map(|&c| c as f64 / total).collect()
+    }
+
+    /// `None` if nothing has been recorded.
+    pub fn mean(&self) -> Option<f64> {
+        if self.total() == 0 {
+            return None;
+        }
+        Some(self.moment(|v| v))
+    }
+
+    /// Population variance; `None` if nothing has been recorded.
+    pub fn variance(&self) -> Option<f64> {
+        let mean = self.mean()?;
+        Some(self.moment(|v| (v - mean).powi(2)))
+    }
+
+    fn moment(&self, f: impl Fn(f64) -> f64) -> f64 {
+        let weighted: f64 = self
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(v, &c)| c as f64 * f(v as f64))
+            .sum();
+        weighted / self.total() as f64
+    }
+}
+
+/// Running mean and variance of a series of values, by Welford's algorithm,
+/// without keeping the values.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EnergyStats {
+    count: u64,
+    mean: f64,
+    /// Sum of squared deviations from the current mean.
+    m2: f64,
+}
+
+impl EnergyStats {
+    pub fn new() -> EnergyStats {
+        EnergyStats::default()
+    }
+
+    pub fn record(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// `None` if nothing has been recorded.
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then_some(self.mean)
+    }
+
+    /// Population variance, like `Histogram::variance`; `None` if nothing
+    /// has been recorded.
+    pub fn variance(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.m2 / self.count as f64)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{BathId, NoOp, ReflectBath, Rule, Simulation, World};
+
+    #[test]
+    fn test_stats() {
+        let mut histogram = Histogram::new(3);
+        assert_eq!(histogram.mean(), None);
+        assert_eq!(histogram.variance(), None);
+        assert_eq!(histogram.frequencies(), vec![0.0; 4]);
+        for v in [1, 3, 3, 1] {
+            histogram.record(v);
+        }
+        assert_eq!(histogram.counts(), &[0, 2, 0, 2]);
+        assert_eq!(histogram.frequencies(), vec![0.0, 0.5, 0.0, 0.5]);
+        assert_eq!(histogram.mean(), Some(2.0));
+        assert_eq!(histogram.variance(), Some(1.0));
+        histogram.record(5);
+        assert_eq!(histogram.counts(), &[0, 2, 0, 2, 0, 1]);
+    }
+
+    #[test]
+    fn test_pinned_battery() {
+        let world = World::builder()
+            .battery([true, false, true, false])
+            .hot_bath([true, true, false, true, false])
+            .build();
+        let rules = vec![Box::new(ReflectBath {
+            subsystem: BathId::Hot,
+        }) as Box<_>];
+        let mut sim = Simulation::new(world, rules);
+        sim.step_n(25);
+        let histogram = sim.battery_histogram();
+        assert_eq!(histogram.counts(), &[0, 0, 25, 0, 0]);
+        assert_eq!(histogram.frequencies(), vec![0.0, 0.0, 1.0, 0.0, 0.0]);
+        assert_eq!(histogram.mean(), Some(2.0));
+        assert_eq!(histogram.variance(), Some(0.0));
+    }
+
+    #[test]
+    fn test_energy_stats() {
+        let mut stats = EnergyStats::new();
+        assert_eq!(stats.mean(), None);
+        assert_eq!(stats.variance(), None);
+        for v in [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0] {
+            stats.record(v);
+        }
+        assert_eq!(stats.count(), 8);
+        assert_eq!(stats.mean(), Some(5.0));
+        assert_eq!(stats.variance(), Some(4.0));
+    }
+
+    /// Occupies the first empty hot cell each step. Not reversible; only
+    /// for tests.
+    #[derive(Clone)]
+    struct Heat;
+
+    impl Rule for Heat {
+        fn step(&self, world: &mut World) {
+            let empty = world.hot_bath().iter().position(|b| !b);
+            if let Some(i) = empty {
+                world.subsystem_mut(BathId::Hot).set(i, true);
+            }
+        }
+
+        fn inverse(&self) -> Box<dyn Rule> {
+            Box::new(NoOp)
+        }
+
+        fn clone_box(&self) -> Box<dyn Rule> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn test_simulation_energy_stats() {
+        let world = World::builder()
+            .battery([true])
+            .hot_bath([false; 3])
+            .build();
+        let mut sim = Simulation::new(world, vec![Box::new(Heat)]);
+        sim.step_n(4);
+        // Total energy after each step: 2, 3, 4, 4.
+        let stats = sim.energy_stats();
+        assert_eq!(stats.count(), 4);
+        assert_eq!(stats.mean(), Some(3.25));
+        assert_eq!(stats.variance(), Some(0.6875));
+        sim.step_back();
+        assert_eq!(sim.energy_stats().count(), 4);
+    }
+}
@@ -0,0 +1,422 @@
+use crate::{permute_in_place, BathId, RuleError, SimConfig, World};
+
+/// Occupation number of a single site.
+pub type Level = u8;
+
+/// Like [`World`], but each site holds any number of quanta up to
+/// `max_level` (by default `Level::MAX`) instead of just zero or one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LevelWorld {
+    pub t: i64,
+    names: Vec<String>,
+    subsystems: Vec<Vec<Level>>,
+    max_level: Level,
+}
+
+impl LevelWorld {
+    /// `World::from_config(config)` with each site's capacity set to
+    /// `config.max_level`.
+    pub fn from_config(config: &SimConfig) -> LevelWorld {
+        LevelWorld::from(&World::from_config(config)).with_max_level(config.max_level)
+    }
+
+    /// Caps every site at `max_level` quanta for `checked_add` and the
+    /// rules that use it. Sites already over it are left alone.
+    pub fn with_max_level(mut self, max_level: Level) -> Self {
+        self.max_level = max_level;
+        self
+    }
+
+    pub fn max_level(&self) -> Level {
+        self.max_level
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn subsystem(&self, id: BathId) -> &[Level] {
+        &self.subsystems[id.index()]
+    }
+
+    pub fn subsystem_mut(&mut self, id: BathId) -> &mut Vec<Level> {
+        &mut self.subsystems[id.index()]
+    }
+
+    pub fn get(&self, (id, i): (BathId, usize)) -> Level {
+        self.subsystem(id)[i]
+    }
+
+    pub fn set(&mut self, (id, i): (BathId, usize), level: Level) {
+        self.subsystem_mut(id)[i] = level;
+    }
+
+    /// Adds `quanta` to the site at `(id, i)`, or leaves it alone and
+    /// returns `RuleError::Overflow` if that would take it past
+    /// `max_level`.
+    pub fn checked_add(&mut self, (id, i): (BathId, usize), quanta: u16) -> Result<(), RuleError> {
+        let level = self.get((id, i)) as u16 + quanta;
+        if level > self.max_level as u16 {
+            return Err(RuleError::Overflow {
+                subsystem: id,
+                index: i,
+                level,
+                max: self.max_level,
+            });
+        }
+        self.set((id, i), level as Level);
+        Ok(())
+    }
+
+    pub fn energy(&self, id: BathId) -> usize {
+        self.subsystem(id).iter().map(|&l| l as usize).sum()
+    }
+
+    pub fn total_energy(&self) -> usize {
+        self.subsystems.iter().flatten().map(|&l| l as usize).sum()
+    }
+}
+
+impl From<&World> for LevelWorld {
+    fn from(world: &World) -> Self {
+        LevelWorld {
+            t: world.t,
+            names: world.subsystems().iter().map(|s| s.name.clone()).collect(),
+            subsystems: world
+                .subsystems()
+                .iter()
+                .map(|s| s.cells.iter().map(Level::from).collect())
+                .collect(),
+            max_level: Level::MAX,
+        }
+    }
+}
+
+/// Converting back only works while every site holds at most one quantum;
+/// otherwise the offending site is returned.
+impl TryFrom<&LevelWorld> for World {
+    type Error = (BathId, usize);
+
+    fn try_from(levels: &LevelWorld) -> Result<Self, Self::Error> {
+        let ids = (0..levels.subsystems.len()).map(BathId::from_index);
+        for (id, sites) in ids.clone().zip(&levels.subsystems) {
+            if let Some(i) = sites.iter().position(|&l| l > 1) {
+                return Err((id, i));
+            }
+        }
+        let bits = |id: BathId| {
+            levels.subsystems[id.index()]
+                .iter()
+                .map(|&l| l == 1)
+                .collect::<crate::BitBath>()
+        };
+        let mut world =
+            World::classic(bits(BathId::Battery), bits(BathId::Hot), bits(BathId::Cold));
+        world.t = levels.t;
+        for id in ids.skip(3) {
+            world.add_subsystem(levels.names[id.index()].clone(), bits(id));
+        }
+        Ok(world)
+    }
+}
+
+/// The multi-level counterpart of [`crate::Rule`].
+pub trait LevelRule {
+    fn step(&self, world: &mut LevelWorld);
+    fn inverse(&self) -> Box<dyn LevelRule>;
+
+    /// Like `step`, but reports a site pushed past `LevelWorld::max_level`
+    /// as an error instead of panicking. Rules that change levels override
+    /// this; the rest can't overflow.
+    fn try_step(&self, world: &mut LevelWorld) -> Result<(), RuleError> {
+        self.step(world);
+        Ok(())
+    }
+}
+
+impl LevelRule for Vec<Box<dyn LevelRule>> {
+    fn step(&self, world: &mut LevelWorld) {
+        for rule in self {
+            rule.step(world);
+        }
+    }
+
+    /// Stops at the first rule that fails, leaving the earlier ones applied.
+    fn try_step(&self, world: &mut LevelWorld) -> Result<(), RuleError> {
+        self.iter().try_for_each(|rule| rule.try_step(world))
+    }
+
+    fn inverse(&self) -> Box<dyn LevelRule> {
+        Box::new(self.iter().rev().map(|r| r.inverse()).collect::<Vec<_>>())
+    }
+}
+
+/// Rearranges the sites of each subsystem, carrying their levels along.
+#[derive(Clone, Debug)]
+pub struct LevelPermute {
+    pub permutations: Vec<Vec<usize>>,
+}
+
+impl LevelRule for LevelPermute {
+    fn step(&self, world: &mut LevelWorld) {
+        for (sites, permutation) in world.subsystems.iter_mut().zip(&self.permutations) {
+            permute_in_place(permutation, sites);
+        }
+    }
+
+    fn inverse(&self) -> Box<dyn LevelRule> {
+        Box::new(LevelPermute {
+            permutations: self
+                .permutations
+                .iter()
+                .map(|p| crate::invert_permutation(p))
+                .collect(),
+        })
+    }
+}
+
+/// Moves one quantum from `from` to `to`.
+///
+/// To stay a bijection, the pair of sites cycles through every split of
+/// their combined energy that fits under `LevelWorld::max_level`: when
+/// `from` has nothing left to give (or `to` is full), the step instead moves
+/// as many quanta as possible back into `from`. The inverse hops the other
+/// way. Stepping a pair that already holds more than twice the maximum
+/// between them overflows; `step` panics on it.
+///
+/// `from` and `to` must be different sites, which `new` checks.
+#[derive(Clone, Copy, Debug)]
+pub struct Hop {
+    pub from: (BathId, usize),
+    pub to: (BathId, usize),
+}
+
+impl Hop {
+    pub fn new(from: (BathId, usize), to: (BathId, usize)) -> Hop {
+        assert_ne!(
+            from, to,
+            "hopping a site onto itself would double its energy"
+        );
+        Hop { from, to }
+    }
+}
+
+impl LevelRule for Hop {
+    fn step(&self, world: &mut LevelWorld) {
+        self.try_step(world).unwrap_or_else(|e| panic!("{e}"));
+    }
+
+    fn try_step(&self, world: &mut LevelWorld) -> Result<(), RuleError> {
+        let (x, y) = (world.get(self.from), world.get(self.to));
+        let sum = x as u16 + y as u16;
+        let max = world.max_level() as u16;
+        let (lo, hi) = (sum.saturating_sub(max), sum.min(max));
+        // A site over the maximum (say, after `with_max_level` lowered it)
+        // is brought back under it like an empty `from`.
+        let new_x = if x as u16 <= lo || x as u16 > hi {
+            hi
+        } else {
+            x as u16 - 1
+        };
+        // Empty both sites and refill them, so a split that doesn't fit is
+        // caught rather than wrapped.
+        world.set(self.from, 0);
+        world.set(self.to, 0);
+        let result = world
+            .checked_add(self.from, new_x)
+            .and_then(|()| world.checked_add(self.to, sum - new_x));
+        if result.is_err() {
+            world.set(self.from, x);
+            world.set(self.to, y);
+        }
+        result
+    }
+
+    fn inverse(&self) -> Box<dyn LevelRule> {
+        Box::new(Hop {
+            from: self.to,
+            to: self.from,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::generate_random_permutation;
+
+    fn world() -> LevelWorld {
+        let mut levels = LevelWorld::from(&World::classic([true, false], [true, true, false], []));
+        levels.subsystem_mut(BathId::Hot)[2] = 7;
+        levels.subsystem_mut(BathId::Battery)[1] = 3;
+        levels
+    }
+
+    #[test]
+    fn test_round_trip_bool_world() {
+        let mut world = World::builder()
+            .t(9)
+            .battery([true, false])
+            .hot_bath_half_full(6)
+            .extra("warm", [false, true])
+            .build();
+        let levels = LevelWorld::from(&world);
+        assert_eq!(levels.total_energy(), world.total_energy());
+        assert_eq!(World::try_from(&levels), Ok(world.clone()));
+
+        world.t = 0;
+        let mut levels = LevelWorld::from(&world);
+        levels.set((BathId::Hot, 4), 2);
+        assert_eq!(World::try_from(&levels), Err((BathId::Hot, 4)));
+    }
+
+    #[test]
+    fn test_permute_preserves_levels() {
+        let mut world = world();
+        let rule = LevelPermute {
+            permutations: vec![
+                generate_random_permutation(2, 0),
+                generate_random_permutation(3, 1),
+            ],
+        };
+        let sorted = |w: &LevelWorld, id| {
+            let mut levels = w.subsystem(id).to_vec();
+            levels.sort();
+            levels
+        };
+        let before = [sorted(&world, BathId::Battery), sorted(&world, BathId::Hot)];
+        for _ in 0..10 {
+            rule.step(&mut world);
+            assert_eq!(
+                [sorted(&world, BathId::Battery), sorted(&world, BathId::Hot)],
+                before
+            );
+        }
+    }
+
+    #[test]
+    fn test_hop() {
+        let mut world = world();
+        let hop = Hop::new((BathId::Hot, 2), (BathId::Battery, 1));
+        hop.step(&mut world);
+        assert_eq!((world.get(hop.from), world.get(hop.to)), (6, 4));
+        assert_eq!(world.total_energy(), 13);
+
+        let start = world.clone();
+        for _ in 0..20 {
+            hop.step(&mut world);
+        }
+        let inverse = hop.inverse();
+        for _ in 0..20 {
+            inverse.step(&mut world);
+        }
+        assert_eq!(world, start);
+    }
+
+    #[test]
+    fn test_checked_add() {
+        let mut world = world().with_max_level(9);
+        let site = (BathId::Hot, 2);
+        assert_eq!(world.checked_add(site, 2), Ok(()));
+        assert_eq!(world.get(site), 9);
+        assert_eq!(world.checked_add(site, 0), Ok(()));
+        assert_eq!(
+            world.checked_add(site, 1),
+            Err(RuleError::Overflow {
+                subsystem: BathId::Hot,
+                index: 2,
+                level: 10,
+                max: 9,
+            })
+        );
+        assert_eq!(world.get(site), 9);
+
+        let mut world = self::world();
+        world.set(site, Level::MAX);
+        assert!(world.checked_add(site, 1).is_err());
+        assert_eq!(
+            world.checked_add(site, 300).unwrap_err().to_string(),
+            "hot cell 2 would hold 555 quanta, over the maximum of 255"
+        );
+    }
+
+    #[test]
+    fn test_hop_at_capacity() {
+        // 7 + 3 quanta under a cap of 5 cycle through the splits (5, 5)
+        // only; under 6, through (6, 4), (5, 5), (4, 6).
+        let hop = Hop::new((BathId::Hot, 2), (BathId::Battery, 1));
+        let mut world = world().with_max_level(6);
+        hop.step(&mut world);
+        assert_eq!((world.get(hop.from), world.get(hop.to)), (6, 4));
+        let mut splits = Vec::new();
+        for _ in 0..3 {
+            hop.step(&mut world);
+            splits.push((world.get(hop.from), world.get(hop.to)));
+        }
+        assert_eq!(splits, [(5, 5), (4, 6), (6, 4)]);
+
+        let mut world = self::world().with_max_level(5);
+        world.set(hop.from, 5);
+        world.set(hop.to, 5);
+        hop.step(&mut world);
+        assert_eq!((world.get(hop.from), world.get(hop.to)), (5, 5));
+    }
+
+    #[test]
+    fn test_hop_above_capacity() {
+        let hop = Hop::new((BathId::Hot, 2), (BathId::Battery, 1));
+        // 10 quanta can't fit in two sites capped at 4.
+        let mut world = world().with_max_level(4);
+        let before = world.clone();
+        assert_eq!(
+            hop.try_step(&mut world),
+            Err(RuleError::Overflow {
+                subsystem: BathId::Battery,
+                index: 1,
+                level: 6,
+                max: 4,
+            })
+        );
+        assert_eq!(world, before);
+        let rules: Vec<Box<dyn LevelRule>> = vec![Box::new(hop)];
+        assert!(rules.try_step(&mut world).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "over the maximum of 4")]
+    fn test_hop_step_panics_above_capacity() {
+        Hop::new((BathId::Hot, 2), (BathId::Battery, 1)).step(&mut world().with_max_level(4));
+    }
+
+    #[test]
+    fn test_from_config() {
+        let config = SimConfig {
+            battery_size: 3,
+            bath_size: 4,
+            max_level: 2,
+            ..SimConfig::default()
+        };
+        let world = LevelWorld::from_config(&config);
+        assert_eq!(world.max_level(), 2);
+        assert_eq!(
+            world.total_energy(),
+            World::from_config(&config).total_energy()
+        );
+        assert_eq!(LevelWorld::from(&World::default()).max_level(), Level::MAX);
+    }
+
+    #[test]
+    fn test_hop_wraps_when_empty() {
+        let mut world = world();
+        world.set((BathId::Hot, 0), 0);
+        world.set((BathId::Hot, 1), 5);
+        Hop::new((BathId::Hot, 0), (BathId::Hot, 1)).step(&mut world);
+        assert_eq!(world.subsystem(BathId::Hot)[..2], [5, 0]);
+    }
+
+    #[test]
+    #[should_panic(expected = "would double its energy")]
+    fn test_hop_onto_itself() {
+        Hop::new((BathId::Hot, 1), (BathId::Hot, 1));
+    }
+}
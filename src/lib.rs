@@ -0,0 +1,4134 @@
+use std::{cell::RefCell, collections::HashMap, fmt::Display, ops::Range, rc::Rc};
+
+use rand::{seq::SliceRandom, Rng, RngCore, SeedableRng};
+
+mod bitbath;
+mod current;
+mod cycle;
+mod diff;
+mod ensemble;
+mod footprint;
+#[cfg(feature = "image")]
+mod frame;
+mod grid;
+mod heat;
+mod histogram;
+mod levels;
+mod lockstep;
+mod observables;
+mod parse;
+mod phase_space;
+mod simulation;
+mod snapshot;
+mod swap_rule;
+mod topology;
+mod trajectory;
+
+pub use bitbath::BitBath;
+pub use current::CurrentMeter;
+pub use cycle::run_cycles;
+pub use diff::{occupation_distance, ShapeError, WorldDiff};
+pub use ensemble::{mean_entropy_production, run_ensemble, Stats};
+pub use footprint::Footprint;
+#[cfg(feature = "image")]
+pub use frame::{render_frame, render_frame_highlighting, save_frames};
+pub use grid::GridError;
+pub use heat::HeatLedger;
+pub use histogram::{EnergyStats, Histogram};
+pub use levels::{Hop, Level, LevelPermute, LevelRule, LevelWorld};
+pub use lockstep::run_lockstep;
+pub use observables::{
+    autocorrelation, carnot_efficiency, effective_temperature, moving_average, occupation_entropy,
+};
+pub use parse::{expect_args, parse_rules, Arg, ParseError, RuleFactory, RuleRegistry};
+pub use phase_space::{state_key, StateCounter, MAX_STATE_CELLS};
+#[cfg(feature = "serde")]
+pub use simulation::CheckpointError;
+pub use simulation::{conserve_total_energy, RunReport, Simulation, StateIter};
+pub use snapshot::{Snapshot, SnapshotDiff};
+pub use topology::Topology;
+pub use trajectory::{render_timeline, PowerOfTwoSampler, Sampling, Trajectory};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimConfig {
+    pub battery_size: usize,
+    pub bath_size: usize,
+    pub n_steps: u64,
+    /// The fraction of the hot bath's cells that start occupied, in `[0, 1]`.
+    pub hot_fill: f64,
+    /// The fraction of the cold bath's cells that start occupied, in `[0, 1]`.
+    pub cold_fill: f64,
+    /// The most quanta a site of a `LevelWorld::from_config` world holds.
+    pub max_level: Level,
+    /// How each bath's occupied cells are laid out.
+    pub fill_pattern: FillPattern,
+}
+
+/// Where a bath's occupied cells go when it's filled to some fraction; see
+/// `SimConfig`. Every pattern occupies exactly `round(fill * n)` of the `n`
+/// cells.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FillPattern {
+    /// A solid block at the start of the bath.
+    #[default]
+    Block,
+    /// Spread out as evenly as possible, starting with cell 0.
+    Interleaved,
+    /// At positions drawn from `seed` plus the bath's `BathId::index`, so
+    /// the hot and cold baths get different arrangements.
+    Random { seed: u64 },
+}
+
+impl FillPattern {
+    /// `n` cells of `bath` with `fill` of them occupied. Panics unless
+    /// `fill` is in [0, 1].
+    pub fn fill(self, bath: BathId, n: usize, fill: f64) -> BitBath {
+        check_fill("fill", fill);
+        let occupied = (fill * n as f64).round() as usize;
+        match self {
+            FillPattern::Block => (0..n).map(|i| i < occupied).collect(),
+            // Cell `i` is occupied when `i * occupied` passes a multiple of
+            // `n`, which happens exactly `occupied` times.
+            FillPattern::Interleaved => (0..n).map(|i| i * occupied % n < occupied).collect(),
+            FillPattern::Random { seed } => {
+                let mut bath_cells = BitBath::new(n);
+                let positions =
+                    generate_random_permutation(n, seed.wrapping_add(bath.index() as u64));
+                for &i in &positions[..occupied] {
+                    bath_cells.set(i, true);
+                }
+                bath_cells
+            }
+        }
+    }
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        SimConfig {
+            battery_size: 20,
+            bath_size: 200,
+            n_steps: 1000000,
+            hot_fill: 0.5,
+            cold_fill: 0.0,
+            max_level: Level::MAX,
+            fill_pattern: FillPattern::Block,
+        }
+    }
+}
+
+/// Names one of a world's subsystems. Every world has a battery, a hot bath,
+/// and a cold bath; any further baths are numbered from zero as `Extra`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BathId {
+    Battery,
+    Hot,
+    Cold,
+    Extra(usize),
+}
+
+impl BathId {
+    pub fn index(self) -> usize {
+        match self {
+            BathId::Battery => 0,
+            BathId::Hot => 1,
+            BathId::Cold => 2,
+            BathId::Extra(i) => 3 + i,
+        }
+    }
+
+    pub fn from_index(index: usize) -> BathId {
+        match index {
+            0 => BathId::Battery,
+            1 => BathId::Hot,
+            2 => BathId::Cold,
+            i => BathId::Extra(i - 3),
+        }
+    }
+}
+
+impl Display for BathId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BathId::Battery => write!(f, "battery"),
+            BathId::Hot => write!(f, "hot"),
+            BathId::Cold => write!(f, "cold"),
+            BathId::Extra(i) => write!(f, "extra{i}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Subsystem {
+    pub name: String,
+    pub cells: BitBath,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct World {
+    pub t: i64,
+    subsystems: Vec<Subsystem>,
+}
+
+impl World {
+    /// The three-subsystem world everything started out as, at `t = 0`.
+    pub fn classic(
+        battery: impl Into<BitBath>,
+        hot_bath: impl Into<BitBath>,
+        cold_bath: impl Into<BitBath>,
+    ) -> World {
+        World {
+            t: 0,
+            subsystems: vec![
+                Subsystem {
+                    name: BathId::Battery.to_string(),
+                    cells: battery.into(),
+                },
+                Subsystem {
+                    name: BathId::Hot.to_string(),
+                    cells: hot_bath.into(),
+                },
+                Subsystem {
+                    name: BathId::Cold.to_string(),
+                    cells: cold_bath.into(),
+                },
+            ],
+        }
+    }
+
+    /// The starting state `main` uses: an empty battery, a half-full hot
+    /// bath, and an empty cold bath. Both baths have exactly `bath_size`
+    /// cells; for odd sizes the hot bath gets the extra occupied cell (see
+    /// `WorldBuilder::hot_bath_half_full`).
+    pub fn new(battery_size: usize, bath_size: usize) -> World {
+        World::builder()
+            .battery_empty(battery_size)
+            .hot_bath_half_full(bath_size)
+            .cold_bath_empty(bath_size)
+            .build()
+    }
+
+    /// An empty battery and two baths of `config.bath_size` cells with
+    /// `round(fill * bath_size)` cells occupied, laid out by
+    /// `config.fill_pattern`. The defaults give the same world as
+    /// `World::new`.
+    ///
+    /// Panics if either fill is outside `[0, 1]`.
+    pub fn from_config(config: &SimConfig) -> World {
+        check_fills(config);
+        World::builder()
+            .battery_empty(config.battery_size)
+            .hot_bath(
+                config
+                    .fill_pattern
+                    .fill(BathId::Hot, config.bath_size, config.hot_fill),
+            )
+            .cold_bath(
+                config
+                    .fill_pattern
+                    .fill(BathId::Cold, config.bath_size, config.cold_fill),
+            )
+            .build()
+    }
+
+    /// Like `from_config`, but each bath cell is occupied independently with
+    /// probability equal to its bath's fill, so there are no spatial
+    /// correlations and the occupied counts are only right on average.
+    ///
+    /// Panics if either fill is outside `[0, 1]`.
+    pub fn thermal<R: RngCore>(config: &SimConfig, rng: &mut R) -> World {
+        check_fills(config);
+        let hot_bath = thermal_bath(config.bath_size, config.hot_fill, rng);
+        let cold_bath = thermal_bath(config.bath_size, config.cold_fill, rng);
+        World::builder()
+            .battery_empty(config.battery_size)
+            .hot_bath(hot_bath)
+            .cold_bath(cold_bath)
+            .build()
+    }
+
+    pub fn builder() -> WorldBuilder {
+        WorldBuilder::default()
+    }
+
+    /// Appends a bath after the existing ones and returns its id.
+    pub fn add_subsystem(&mut self, name: impl Into<String>, cells: impl Into<BitBath>) -> BathId {
+        self.subsystems.push(Subsystem {
+            name: name.into(),
+            cells: cells.into(),
+        });
+        BathId::from_index(self.subsystems.len() - 1)
+    }
+
+    pub fn subsystems(&self) -> &[Subsystem] {
+        &self.subsystems
+    }
+
+    /// Whether the two worlds are in the same physical state: the same
+    /// subsystems with the same cells, whatever their `t`s. `==` also
+    /// compares `t`.
+    pub fn contents_eq(&self, other: &World) -> bool {
+        self.subsystems == other.subsystems
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = BathId> {
+        (0..self.subsystems.len()).map(BathId::from_index)
+    }
+
+    pub fn id_of(&self, name: &str) -> Option<BathId> {
+        self.subsystems
+            .iter()
+            .position(|s| s.name == name)
+            .map(BathId::from_index)
+    }
+
+    pub fn subsystem(&self, id: BathId) -> &BitBath {
+        &self.subsystems[id.index()].cells
+    }
+
+    /// Only one subsystem can be borrowed mutably this way at a time. To
+    /// work on two at once use `subsystem_pair_mut`, or `swap_cells` for
+    /// single cells.
+    pub fn subsystem_mut(&mut self, id: BathId) -> &mut BitBath {
+        &mut self.subsystems[id.index()].cells
+    }
+
+    /// Mutable borrows of two different subsystems at once. Panics if `a`
+    /// and `b` are the same, since that would alias.
+    pub fn subsystem_pair_mut(&mut self, a: BathId, b: BathId) -> (&mut BitBath, &mut BitBath) {
+        let (i, j) = (a.index(), b.index());
+        assert_ne!(i, j, "can't borrow {a} mutably twice");
+        let (lo, hi) = self.subsystems.split_at_mut(i.max(j));
+        let (low, high) = (&mut lo[i.min(j)].cells, &mut hi[0].cells);
+        if i < j {
+            (low, high)
+        } else {
+            (high, low)
+        }
+    }
+
+    /// Mutable borrows of the battery and both baths at once.
+    pub fn view(&mut self) -> WorldView<'_> {
+        let [battery, hot, cold, ..] = &mut self.subsystems[..] else {
+            panic!("world is missing one of its classic subsystems");
+        };
+        WorldView {
+            battery: &mut battery.cells,
+            hot: &mut hot.cells,
+            cold: &mut cold.cells,
+        }
+    }
+
+    pub fn cells_mut(&mut self) -> impl Iterator<Item = &mut BitBath> {
+        self.subsystems.iter_mut().map(|s| &mut s.cells)
+    }
+
+    pub fn battery(&self) -> &BitBath {
+        self.subsystem(BathId::Battery)
+    }
+
+    pub fn hot_bath(&self) -> &BitBath {
+        self.subsystem(BathId::Hot)
+    }
+
+    pub fn cold_bath(&self) -> &BitBath {
+        self.subsystem(BathId::Cold)
+    }
+
+    pub fn get(&self, (id, i): (BathId, usize)) -> bool {
+        self.subsystem(id).get(i)
+    }
+
+    /// Swaps two cells, which may be in the same subsystem or different ones.
+    pub fn swap_cells(&mut self, a: (BathId, usize), b: (BathId, usize)) {
+        let (x, y) = (self.get(a), self.get(b));
+        self.subsystem_mut(a.0).set(a.1, y);
+        self.subsystem_mut(b.0).set(b.1, x);
+    }
+
+    pub fn energy(&self, id: BathId) -> usize {
+        self.subsystem(id).count_ones()
+    }
+
+    /// How many cells in `range` of subsystem `id` are occupied, e.g. to
+    /// compare the halves of a bath.
+    pub fn count_range(&self, id: BathId, range: Range<usize>) -> Result<usize, RangeError> {
+        let bath = self
+            .subsystems
+            .get(id.index())
+            .ok_or(RangeError::MissingSubsystem { subsystem: id })?;
+        let len = bath.cells.len();
+        if range.start > range.end || range.end > len {
+            return Err(RangeError::OutOfBounds {
+                subsystem: id,
+                range,
+                len,
+            });
+        }
+        Ok(bath.cells.count_range(range))
+    }
+
+    /// How many neighbouring cells of subsystem `id` differ: one per
+    /// boundary between an occupied run and an empty one, so a block-filled
+    /// bath has exactly one (none if it's empty or full) and a thoroughly
+    /// mixed one has about half its length.
+    pub fn domain_walls(&self, id: BathId) -> usize {
+        self.subsystem(id).domain_walls()
+    }
+
+    /// How many more occupied cells the first half of subsystem `id` has
+    /// than the second, so a bath whose energy has piled up at the start is
+    /// positive and one at equilibrium is near zero. For an odd length the
+    /// middle cell is in neither half.
+    pub fn gradient(&self, id: BathId) -> i64 {
+        let bath = self.subsystem(id);
+        let half = bath.len() / 2;
+        bath.count_range(0..half) as i64 - bath.count_range(bath.len() - half..bath.len()) as i64
+    }
+
+    pub fn battery_energy(&self) -> usize {
+        self.energy(BathId::Battery)
+    }
+
+    pub fn hot_bath_energy(&self) -> usize {
+        self.energy(BathId::Hot)
+    }
+
+    pub fn cold_bath_energy(&self) -> usize {
+        self.energy(BathId::Cold)
+    }
+
+    pub fn total_energy(&self) -> usize {
+        self.subsystems.iter().map(|s| s.cells.count_ones()).sum()
+    }
+}
+
+/// A battery, hot bath, and cold bath with no cells, at `t = 0`.
+impl Default for World {
+    fn default() -> Self {
+        World::classic(BitBath::new(0), BitBath::new(0), BitBath::new(0))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct WorldBuilder {
+    t: i64,
+    battery: BitBath,
+    hot_bath: BitBath,
+    cold_bath: BitBath,
+    extra: Vec<(String, BitBath)>,
+}
+
+impl WorldBuilder {
+    pub fn t(mut self, t: i64) -> Self {
+        self.t = t;
+        self
+    }
+
+    pub fn battery(mut self, battery: impl Into<BitBath>) -> Self {
+        self.battery = battery.into();
+        self
+    }
+
+    pub fn hot_bath(mut self, hot_bath: impl Into<BitBath>) -> Self {
+        self.hot_bath = hot_bath.into();
+        self
+    }
+
+    pub fn cold_bath(mut self, cold_bath: impl Into<BitBath>) -> Self {
+        self.cold_bath = cold_bath.into();
+        self
+    }
+
+    /// Adds a bath beyond the classic three; they get `BathId::Extra(0)`,
+    /// `BathId::Extra(1)`, ... in the order they're added.
+    pub fn extra(mut self, name: impl Into<String>, cells: impl Into<BitBath>) -> Self {
+        self.extra.push((name.into(), cells.into()));
+        self
+    }
+
+    pub fn battery_empty(self, n: usize) -> Self {
+        self.battery(BitBath::new(n))
+    }
+
+    /// `n` cells, the first `ceil(n/2)` of them occupied. Rounding up means
+    /// an odd-sized bath is never short of energy, and no cell is dropped.
+    pub fn hot_bath_half_full(self, n: usize) -> Self {
+        self.hot_bath(half_full(n))
+    }
+
+    pub fn cold_bath_empty(self, n: usize) -> Self {
+        self.cold_bath(BitBath::new(n))
+    }
+
+    pub fn build(self) -> World {
+        let mut world = World::classic(self.battery, self.hot_bath, self.cold_bath);
+        world.t = self.t;
+        for (name, cells) in self.extra {
+            world.add_subsystem(name, cells);
+        }
+        world
+    }
+}
+
+fn half_full(n: usize) -> BitBath {
+    (0..n).map(|i| i < n.div_ceil(2)).collect()
+}
+
+/// `n` cells, each occupied independently with probability `fill`.
+fn thermal_bath<R: RngCore>(n: usize, fill: f64, rng: &mut R) -> BitBath {
+    (0..n).map(|_| rng.gen_bool(fill)).collect()
+}
+
+fn check_fills(config: &SimConfig) {
+    check_fill("hot_fill", config.hot_fill);
+    check_fill("cold_fill", config.cold_fill);
+}
+
+fn check_fill(name: &str, fill: f64) {
+    assert!(
+        (0.0..=1.0).contains(&fill),
+        "{name} must be in [0, 1], got {fill}"
+    );
+}
+
+/// A world's classic three subsystems, borrowed mutably together so a rule
+/// can read one while writing another; see `World::view`.
+#[derive(Debug)]
+pub struct WorldView<'a> {
+    pub battery: &'a mut BitBath,
+    pub hot: &'a mut BitBath,
+    pub cold: &'a mut BitBath,
+}
+
+/// How many cells per subsystem `{world:#}` shows when no precision is given.
+pub const DEFAULT_DETAIL_WIDTH: usize = 80;
+
+/// `{world}` prints `t` and each subsystem's occupied/total counts on one
+/// line.
+///
+/// `{world:#}` instead prints `t=<t>` followed by one line per subsystem with
+/// `#` for each occupied cell and `.` for each empty one. Lines are cut off
+/// with `…` after [`DEFAULT_DETAIL_WIDTH`] cells, or after `n` cells with
+/// `{world:#.n}`.
+impl Display for World {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            let width = f.precision().unwrap_or(DEFAULT_DETAIL_WIDTH);
+            write!(f, "t={}", self.t)?;
+            for subsystem in &self.subsystems {
+                writeln!(f)?;
+                for b in subsystem.cells.iter().take(width) {
+                    write!(f, "{}", if b { '#' } else { '.' })?;
+                }
+                if subsystem.cells.len() > width {
+                    write!(f, "…")?;
+                }
+            }
+            return Ok(());
+        }
+        write!(f, "{t:>width$}", t = self.t, width = 5)?;
+        for subsystem in &self.subsystems {
+            write!(
+                f,
+                " [({h:>3}/{l:>3})]",
+                h = subsystem.cells.count_ones(),
+                l = subsystem.cells.len()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub trait Rule {
+    fn step(&self, world: &mut World);
+    fn inverse(&self) -> Box<dyn Rule>;
+    fn clone_box(&self) -> Box<dyn Rule>;
+
+    /// The RNG seed this rule's dynamics depend on, if any. Composite rules
+    /// report the first seed among their parts.
+    fn seed(&self) -> Option<u64> {
+        None
+    }
+
+    /// Checks that every cell and subsystem the rule touches exists in
+    /// `world`, so that stepping it won't panic on an index.
+    fn validate(&self, _world: &World) -> Result<(), RuleError> {
+        Ok(())
+    }
+
+    /// The cells this rule reads or writes; see `Footprint`. Composite rules
+    /// report the union of their parts'.
+    fn footprint(&self) -> Footprint {
+        Footprint::new()
+    }
+
+    /// Runs `self` and then `next` as a single rule, so rule sets can be
+    /// written `a.then(b).then(c)`.
+    fn then<R: Rule + 'static>(self, next: R) -> Box<dyn Rule>
+    where
+        Self: Sized + 'static,
+    {
+        Box::new(Then {
+            first: Box::new(self),
+            second: Box::new(next),
+        })
+    }
+}
+
+impl Clone for Box<dyn Rule> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+impl Rule for Box<dyn Rule> {
+    fn step(&self, world: &mut World) {
+        (**self).step(world);
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        (**self).inverse()
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        (**self).clone_box()
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        (**self).validate(world)
+    }
+
+    fn seed(&self) -> Option<u64> {
+        (**self).seed()
+    }
+
+    fn footprint(&self) -> Footprint {
+        (**self).footprint()
+    }
+}
+
+/// If hot cell `probe` is occupied, swaps battery cell `battery_slot` with
+/// hot cell `hot_slot`.
+///
+/// The indices must be in range for the world the rule runs on; stepping
+/// panics otherwise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProbeAndSwap {
+    pub probe: usize,
+    pub battery_slot: usize,
+    pub hot_slot: usize,
+}
+
+impl ProbeAndSwap {
+    pub fn new(probe: usize, battery_slot: usize, hot_slot: usize) -> ProbeAndSwap {
+        assert_ne!(
+            probe, hot_slot,
+            "probing the swapped cell would make ProbeAndSwap irreversible"
+        );
+        ProbeAndSwap {
+            probe,
+            battery_slot,
+            hot_slot,
+        }
+    }
+
+    pub fn apply(&self, view: WorldView) {
+        if view.hot.get(self.probe) {
+            view.battery
+                .swap_with(self.battery_slot, view.hot, self.hot_slot);
+        }
+    }
+}
+
+impl Default for ProbeAndSwap {
+    fn default() -> Self {
+        ProbeAndSwap::new(0, 1, 1)
+    }
+}
+
+impl Rule for ProbeAndSwap {
+    fn step(&self, world: &mut World) {
+        self.apply(world.view());
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(*self)
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(*self)
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        self.footprint().check("ProbeAndSwap", world)
+    }
+
+    fn footprint(&self) -> Footprint {
+        Footprint::from_cells([
+            (BathId::Hot, self.probe),
+            (BathId::Battery, self.battery_slot),
+            (BathId::Hot, self.hot_slot),
+        ])
+    }
+}
+
+/// `ProbeAndSwap` for the cold bath: if cold cell `probe` is occupied, swaps
+/// battery cell `battery_slot` with cold cell `cold_slot`.
+///
+/// The indices must be in range for the world the rule runs on; stepping
+/// panics otherwise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ColdCouple {
+    pub probe: usize,
+    pub battery_slot: usize,
+    pub cold_slot: usize,
+}
+
+impl ColdCouple {
+    pub fn new(probe: usize, battery_slot: usize, cold_slot: usize) -> ColdCouple {
+        assert_ne!(
+            probe, cold_slot,
+            "probing the swapped cell would make ColdCouple irreversible"
+        );
+        ColdCouple {
+            probe,
+            battery_slot,
+            cold_slot,
+        }
+    }
+}
+
+impl Rule for ColdCouple {
+    fn step(&self, world: &mut World) {
+        if world.get((BathId::Cold, self.probe)) {
+            world.swap_cells(
+                (BathId::Battery, self.battery_slot),
+                (BathId::Cold, self.cold_slot),
+            );
+        }
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(*self)
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(*self)
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        self.footprint().check("ColdCouple", world)
+    }
+
+    fn footprint(&self) -> Footprint {
+        Footprint::from_cells([
+            (BathId::Cold, self.probe),
+            (BathId::Battery, self.battery_slot),
+            (BathId::Cold, self.cold_slot),
+        ])
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Permute {
+    /// One permutation per subsystem, in `BathId::index` order.
+    pub(crate) permutations: Vec<Vec<usize>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PermuteError {
+    OutOfRange {
+        subsystem: BathId,
+        index: usize,
+        len: usize,
+    },
+    Duplicate {
+        subsystem: BathId,
+        index: usize,
+    },
+    WrongLength {
+        subsystem: BathId,
+        expected: usize,
+        len: usize,
+    },
+}
+
+impl Display for PermuteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PermuteError::OutOfRange {
+                subsystem,
+                index,
+                len,
+            } => write!(
+                f,
+                "{subsystem} permutation contains {index}, which is out of range for length {len}"
+            ),
+            PermuteError::Duplicate { subsystem, index } => {
+                write!(f, "{subsystem} permutation contains {index} more than once")
+            }
+            PermuteError::WrongLength {
+                subsystem,
+                expected,
+                len,
+            } => write!(
+                f,
+                "{subsystem} permutation has length {len}, expected {expected}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PermuteError {}
+
+fn check_permutation(subsystem: BathId, permutation: &[usize]) -> Result<(), PermuteError> {
+    let len = permutation.len();
+    let mut seen = vec![false; len];
+    for &index in permutation {
+        if index >= len {
+            return Err(PermuteError::OutOfRange {
+                subsystem,
+                index,
+                len,
+            });
+        }
+        if seen[index] {
+            return Err(PermuteError::Duplicate { subsystem, index });
+        }
+        seen[index] = true;
+    }
+    Ok(())
+}
+
+/// A range `World::count_range` can't count.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RangeError {
+    MissingSubsystem {
+        subsystem: BathId,
+    },
+    OutOfBounds {
+        subsystem: BathId,
+        range: Range<usize>,
+        len: usize,
+    },
+}
+
+impl Display for RangeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RangeError::MissingSubsystem { subsystem } => {
+                write!(f, "the world has no subsystem {subsystem}")
+            }
+            RangeError::OutOfBounds {
+                subsystem,
+                range,
+                len,
+            } => write!(
+                f,
+                "range {range:?} is out of bounds for {subsystem} of length {len}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RangeError {}
+
+/// A rule that needs cells or subsystems a world doesn't have; see
+/// `Rule::validate`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RuleError {
+    MissingSubsystem {
+        rule: &'static str,
+        subsystem: BathId,
+    },
+    OutOfRange {
+        rule: &'static str,
+        subsystem: BathId,
+        index: usize,
+        len: usize,
+    },
+    WrongLength {
+        rule: &'static str,
+        subsystem: BathId,
+        expected: usize,
+        len: usize,
+    },
+    /// Cell `index` of `subsystem` would hold `level` quanta, more than a
+    /// `LevelWorld`'s `max` allows.
+    Overflow {
+        subsystem: BathId,
+        index: usize,
+        level: u16,
+        max: Level,
+    },
+    /// `rule` records what it overwrites in `ancilla`, which must be a
+    /// separate subsystem of at least `needed` cells.
+    Ancilla {
+        rule: &'static str,
+        ancilla: BathId,
+        needed: usize,
+        len: usize,
+    },
+    /// The rule at `position` in a rule set couples `a` and `b`, which a
+    /// `Topology` doesn't allow.
+    Uncoupled {
+        position: usize,
+        a: BathId,
+        b: BathId,
+    },
+}
+
+impl Display for RuleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleError::MissingSubsystem { rule, subsystem } => {
+                write!(
+                    f,
+                    "{rule} uses subsystem {subsystem}, which the world lacks"
+                )
+            }
+            RuleError::OutOfRange {
+                rule,
+                subsystem,
+                index,
+                len,
+            } => write!(
+                f,
+                "{rule} uses {subsystem} cell {index}, which is out of range for length {len}"
+            ),
+            RuleError::WrongLength {
+                rule,
+                subsystem,
+                expected,
+                len,
+            } => write!(
+                f,
+                "{rule} has a {subsystem} permutation of length {len}, expected {expected}"
+            ),
+            RuleError::Overflow {
+                subsystem,
+                index,
+                level,
+                max,
+            } => write!(
+                f,
+                "{subsystem} cell {index} would hold {level} quanta, over the maximum of {max}"
+            ),
+            RuleError::Ancilla {
+                rule,
+                ancilla,
+                needed,
+                len,
+            } => write!(
+                f,
+                "{rule} needs {ancilla} to be a separate ancilla of at least {needed} cells, got {len}"
+            ),
+            RuleError::Uncoupled { position, a, b } => write!(
+                f,
+                "rule {position} couples {a} and {b}, which the topology doesn't allow"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RuleError {}
+
+fn check_subsystem(rule: &'static str, world: &World, subsystem: BathId) -> Result<(), RuleError> {
+    if subsystem.index() >= world.subsystems().len() {
+        return Err(RuleError::MissingSubsystem { rule, subsystem });
+    }
+    Ok(())
+}
+
+fn check_cell(
+    rule: &'static str,
+    world: &World,
+    (subsystem, index): (BathId, usize),
+) -> Result<(), RuleError> {
+    check_subsystem(rule, world, subsystem)?;
+    let len = world.subsystem(subsystem).len();
+    if index >= len {
+        return Err(RuleError::OutOfRange {
+            rule,
+            subsystem,
+            index,
+            len,
+        });
+    }
+    Ok(())
+}
+
+impl Permute {
+    /// Each argument must be a rearrangement of `0..n` for the corresponding
+    /// subsystem's length `n`.
+    pub fn new(
+        battery: Vec<usize>,
+        hot_bath: Vec<usize>,
+        cold_bath: Vec<usize>,
+    ) -> Result<Permute, PermuteError> {
+        Permute::from_permutations(vec![battery, hot_bath, cold_bath])
+    }
+
+    /// Like `new`, but with one permutation per subsystem for worlds with
+    /// extra baths. Subsystems past the end of `permutations` are untouched.
+    pub fn from_permutations(permutations: Vec<Vec<usize>>) -> Result<Permute, PermuteError> {
+        for (i, permutation) in permutations.iter().enumerate() {
+            check_permutation(BathId::from_index(i), permutation)?;
+        }
+        Ok(Permute { permutations })
+    }
+
+    /// A single rule equivalent to `self` followed by `then`. A subsystem
+    /// only one of them covers gets that one's permutation.
+    pub fn compose(&self, then: &Permute) -> Result<Permute, PermuteError> {
+        let n = self.permutations.len().max(then.permutations.len());
+        let mut permutations = Vec::with_capacity(n);
+        for i in 0..n {
+            permutations.push(match (self.permutations.get(i), then.permutations.get(i)) {
+                (Some(first), Some(second)) if first.len() != second.len() => {
+                    return Err(PermuteError::WrongLength {
+                        subsystem: BathId::from_index(i),
+                        expected: first.len(),
+                        len: second.len(),
+                    });
+                }
+                (Some(first), Some(second)) => compose_permutations(first, second),
+                (Some(only), None) | (None, Some(only)) => only.clone(),
+                (None, None) => unreachable!("i is below one of the lengths"),
+            });
+        }
+        Ok(Permute { permutations })
+    }
+}
+
+impl Rule for Permute {
+    fn step(&self, world: &mut World) {
+        for (target, permutation) in world.cells_mut().zip(&self.permutations) {
+            target.permute(permutation);
+        }
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(Permute {
+            permutations: self
+                .permutations
+                .iter()
+                .map(|p| invert_permutation(p))
+                .collect(),
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(self.clone())
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        for (i, permutation) in self.permutations.iter().enumerate() {
+            let subsystem = BathId::from_index(i);
+            check_subsystem("Permute", world, subsystem)?;
+            let expected = world.subsystem(subsystem).len();
+            if permutation.len() != expected {
+                return Err(RuleError::WrongLength {
+                    rule: "Permute",
+                    subsystem,
+                    expected,
+                    len: permutation.len(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn footprint(&self) -> Footprint {
+        Footprint::from_subsystems((0..self.permutations.len()).map(BathId::from_index))
+    }
+}
+
+/// How many permutations `WeirdPermute::step` will cache on its own. Past
+/// this, only `precompute`d permutations are reused, so a long run can't
+/// grow the cache without bound.
+const PERMUTATION_CACHE_CAPACITY: usize = 4096;
+
+type PermutationCache = Rc<RefCell<HashMap<(usize, u64), Rc<Vec<usize>>>>>;
+
+/// Shuffles every subsystem with a permutation drawn from `seed + t`.
+///
+/// Generated permutations are cached by `(length, seed + t)`. Clones and the
+/// inverse share the cache, so stepping back and forth over the same `t`s
+/// only shuffles once.
+#[derive(Clone)]
+pub struct WeirdPermute {
+    pub seed: u64,
+    pub inverted: bool,
+    shuffle: Shuffle,
+    cache: PermutationCache,
+}
+
+/// How a seed becomes a permutation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Shuffle {
+    /// `generate_random_permutation`.
+    #[default]
+    StdRng,
+    /// `fisher_yates_lcg`: faster, lower quality, and a different
+    /// permutation for the same seed.
+    Lcg,
+}
+
+impl Shuffle {
+    pub fn permutation(self, n: usize, seed: u64) -> Vec<usize> {
+        match self {
+            Shuffle::StdRng => generate_random_permutation(n, seed),
+            Shuffle::Lcg => fisher_yates_lcg(n, seed),
+        }
+    }
+}
+
+impl WeirdPermute {
+    pub fn new(seed: u64) -> WeirdPermute {
+        WeirdPermute {
+            seed,
+            inverted: false,
+            shuffle: Shuffle::default(),
+            cache: PermutationCache::default(),
+        }
+    }
+
+    /// Generates permutations with `shuffle` instead. The cache starts over,
+    /// so this doesn't share it with `self`'s clones.
+    pub fn with_shuffle(self, shuffle: Shuffle) -> WeirdPermute {
+        WeirdPermute {
+            shuffle,
+            cache: PermutationCache::default(),
+            ..self
+        }
+    }
+
+    pub fn shuffle(&self) -> Shuffle {
+        self.shuffle
+    }
+
+    /// Fills the cache for every `t` in `ts` and every subsystem length in
+    /// `world`, regardless of the usual capacity limit.
+    pub fn precompute(&self, world: &World, ts: impl IntoIterator<Item = i64>) {
+        let mut cache = self.cache.borrow_mut();
+        for t in ts {
+            let seed = self.seed.wrapping_add_signed(t);
+            for bath in world.subsystems() {
+                let len = bath.cells.len();
+                cache
+                    .entry((len, seed))
+                    .or_insert_with(|| Rc::new(self.shuffle.permutation(len, seed)));
+            }
+        }
+    }
+}
+
+/// `shuffle.permutation(len, seed)`, from `cache` if it's there.
+fn cached_permutation(
+    cache: &PermutationCache,
+    shuffle: Shuffle,
+    len: usize,
+    seed: u64,
+) -> Rc<Vec<usize>> {
+    if let Some(perm) = cache.borrow().get(&(len, seed)) {
+        return perm.clone();
+    }
+    let perm = Rc::new(shuffle.permutation(len, seed));
+    let mut cache = cache.borrow_mut();
+    if cache.len() < PERMUTATION_CACHE_CAPACITY {
+        cache.insert((len, seed), perm.clone());
+    }
+    perm
+}
+
+impl std::fmt::Debug for WeirdPermute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WeirdPermute")
+            .field("seed", &self.seed)
+            .field("inverted", &self.inverted)
+            .field("shuffle", &self.shuffle)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Rule for WeirdPermute {
+    fn step(&self, world: &mut World) {
+        // The inverse runs after `t` has been bumped past the forward step it
+        // undoes, so it must regenerate the permutation for `t - 1`.
+        let t = if self.inverted {
+            world.t.wrapping_sub(1)
+        } else {
+            world.t
+        };
+        for target in world.cells_mut() {
+            let perm = cached_permutation(
+                &self.cache,
+                self.shuffle,
+                target.len(),
+                self.seed.wrapping_add_signed(t),
+            );
+            if self.inverted {
+                target.permute_inverse(&perm);
+            } else {
+                target.permute(&perm);
+            }
+        }
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(WeirdPermute {
+            seed: self.seed,
+            inverted: !self.inverted,
+            shuffle: self.shuffle,
+            cache: self.cache.clone(),
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(self.clone())
+    }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+
+    fn footprint(&self) -> Footprint {
+        Footprint::all()
+    }
+}
+
+/// Shuffles every subsystem with the same permutation each step: subsystem
+/// `i` gets `generate_random_permutation(len, seed + i)`, generated the first
+/// time it's needed.
+#[derive(Clone)]
+pub struct SeededPermute {
+    pub seed: u64,
+    pub inverted: bool,
+    cache: PermutationCache,
+}
+
+impl SeededPermute {
+    pub fn new(seed: u64) -> SeededPermute {
+        SeededPermute {
+            seed,
+            inverted: false,
+            cache: PermutationCache::default(),
+        }
+    }
+}
+
+impl std::fmt::Debug for SeededPermute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SeededPermute")
+            .field("seed", &self.seed)
+            .field("inverted", &self.inverted)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Rule for SeededPermute {
+    fn step(&self, world: &mut World) {
+        for (i, target) in world.cells_mut().enumerate() {
+            let perm = cached_permutation(
+                &self.cache,
+                Shuffle::StdRng,
+                target.len(),
+                self.seed.wrapping_add(i as u64),
+            );
+            if self.inverted {
+                target.permute_inverse(&perm);
+            } else {
+                target.permute(&perm);
+            }
+        }
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(SeededPermute {
+            seed: self.seed,
+            inverted: !self.inverted,
+            cache: self.cache.clone(),
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(self.clone())
+    }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+
+    fn footprint(&self) -> Footprint {
+        Footprint::all()
+    }
+}
+
+/// Shuffles one subsystem with a permutation drawn from `seed + t`, like a
+/// bath in contact with an environment that stirs it at random.
+///
+/// It's only reversible in the seeded sense: the inverse undoes a step by
+/// regenerating the same permutation from the seed and `t`, just as
+/// `WeirdPermute`'s does, so it needs the clock to be where the forward step
+/// left it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Thermalize {
+    pub subsystem: BathId,
+    pub seed: u64,
+    pub inverted: bool,
+}
+
+impl Thermalize {
+    pub fn new(subsystem: BathId, seed: u64) -> Thermalize {
+        Thermalize {
+            subsystem,
+            seed,
+            inverted: false,
+        }
+    }
+}
+
+impl Rule for Thermalize {
+    fn step(&self, world: &mut World) {
+        let t = if self.inverted {
+            world.t.wrapping_sub(1)
+        } else {
+            world.t
+        };
+        let target = world.subsystem_mut(self.subsystem);
+        let perm = generate_random_permutation(target.len(), self.seed.wrapping_add_signed(t));
+        if self.inverted {
+            target.permute_inverse(&perm);
+        } else {
+            target.permute(&perm);
+        }
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(Thermalize {
+            inverted: !self.inverted,
+            ..*self
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(*self)
+    }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        self.footprint().check("Thermalize", world)
+    }
+
+    fn footprint(&self) -> Footprint {
+        Footprint::from_subsystems([self.subsystem])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::World;
+
+    /// The world most rule tests run on: the clock at `t`, a half-full hot
+    /// bath of `hot` cells, and a battery and cold bath of other lengths
+    /// with a few quanta scattered through them.
+    fn sample_world(t: i64, hot: usize) -> World {
+        World::builder()
+            .t(t)
+            .battery([false, true, false, true])
+            .hot_bath_half_full(hot)
+            .cold_bath([false, true, false, false, true, false])
+            .build()
+    }
+
+    mod world_builder {
+        use crate::*;
+        #[test]
+        fn test_defaults() {
+            assert_eq!(
+                World::builder().build(),
+                World::classic(BitBath::new(0), BitBath::new(0), BitBath::new(0))
+            );
+        }
+
+        #[test]
+        fn test_chained() {
+            let world = World::builder()
+                .t(7)
+                .battery([true, false])
+                .hot_bath_half_full(5)
+                .cold_bath(vec![false, true])
+                .build();
+            let mut expected = World::classic(
+                [true, false],
+                [true, true, true, false, false],
+                [false, true],
+            );
+            expected.t = 7;
+            assert_eq!(world, expected);
+        }
+
+        #[test]
+        fn test_extra() {
+            let world = World::builder()
+                .battery([true])
+                .extra("warm", [false, true])
+                .extra("tepid", [true, true, true])
+                .build();
+            assert_eq!(world.subsystems().len(), 5);
+            assert_eq!(world.id_of("tepid"), Some(BathId::Extra(1)));
+            assert_eq!(world.energy(BathId::Extra(0)), 1);
+            assert_eq!(world.total_energy(), 5);
+        }
+    }
+
+    mod display {
+        use super::sample_world;
+        use crate::*;
+
+        #[test]
+        fn test_compact() {
+            assert_eq!(
+                format!("{}", sample_world(42, 6)),
+                "   42 [(  2/  4)] [(  3/  6)] [(  2/  6)]"
+            );
+        }
+
+        #[test]
+        fn test_detailed() {
+            assert_eq!(
+                format!("{:#}", sample_world(42, 6)),
+                "t=42\n.#.#\n###...\n.#..#."
+            );
+        }
+
+        #[test]
+        fn test_detailed_truncated() {
+            assert_eq!(
+                format!("{:#.4}", sample_world(42, 6)),
+                "t=42\n.#.#\n###.…\n.#..…"
+            );
+            let long = World::builder().hot_bath_half_full(200).build();
+            let hot_line = format!("{long:#}").lines().nth(2).unwrap().to_string();
+            assert_eq!(hot_line, "#".repeat(80) + "…");
+        }
+    }
+
+    mod count_range {
+        use crate::*;
+
+        #[test]
+        fn test_ranges() {
+            let world = World::classic([true, false], [true, true, false, true, false], []);
+            assert_eq!(world.count_range(BathId::Hot, 0..5), Ok(3));
+            assert_eq!(world.count_range(BathId::Hot, 2..5), Ok(1));
+            assert_eq!(world.count_range(BathId::Hot, 3..3), Ok(0));
+            assert_eq!(world.count_range(BathId::Cold, 0..0), Ok(0));
+            assert_eq!(world.count_range(BathId::Battery, 0..1), Ok(1));
+        }
+
+        #[test]
+        fn test_out_of_bounds() {
+            let world = World::classic([true, false], [true, true, false, true, false], []);
+            assert_eq!(
+                world.count_range(BathId::Hot, 2..6),
+                Err(RangeError::OutOfBounds {
+                    subsystem: BathId::Hot,
+                    range: 2..6,
+                    len: 5,
+                })
+            );
+            #[allow(clippy::reversed_empty_ranges)]
+            let backwards = 3..1;
+            assert!(world.count_range(BathId::Hot, backwards).is_err());
+            assert_eq!(
+                world
+                    .count_range(BathId::Extra(0), 0..0)
+                    .unwrap_err()
+                    .to_string(),
+                "the world has no subsystem extra0"
+            );
+        }
+    }
+
+    mod domain_walls {
+        use crate::*;
+
+        #[test]
+        fn test_block() {
+            let world = World::from_config(&SimConfig {
+                bath_size: 100,
+                ..SimConfig::default()
+            });
+            assert_eq!(world.domain_walls(BathId::Hot), 1);
+            // Empty or full baths have none.
+            assert_eq!(world.domain_walls(BathId::Cold), 0);
+            assert_eq!(
+                World::builder()
+                    .hot_bath([true; 5])
+                    .build()
+                    .domain_walls(BathId::Hot),
+                0
+            );
+        }
+
+        #[test]
+        fn test_alternating() {
+            for len in [1, 2, 7, 64, 65, 200] {
+                let world = World::builder()
+                    .hot_bath((0..len).map(|i| i % 2 == 0).collect::<Vec<_>>())
+                    .build();
+                assert_eq!(world.domain_walls(BathId::Hot), len - 1);
+            }
+            assert_eq!(World::default().domain_walls(BathId::Hot), 0);
+        }
+    }
+
+    mod gradient {
+        use crate::*;
+
+        #[test]
+        fn test_first_half_full() {
+            let world = World::builder()
+                .hot_bath([true, true, true, false, false, false])
+                .build();
+            assert_eq!(world.gradient(BathId::Hot), 3);
+            let mut reflected = world.clone();
+            ReflectBath {
+                subsystem: BathId::Hot,
+            }
+            .step(&mut reflected);
+            assert_eq!(reflected.gradient(BathId::Hot), -3);
+        }
+
+        #[test]
+        fn test_uniform() {
+            let world = World::builder()
+                .battery([true; 4])
+                .hot_bath([true, false, true, false])
+                .build();
+            assert_eq!(world.gradient(BathId::Battery), 0);
+            assert_eq!(world.gradient(BathId::Hot), 0);
+            assert_eq!(world.gradient(BathId::Cold), 0);
+        }
+
+        #[test]
+        fn test_odd_length_skips_middle() {
+            let world = World::builder()
+                .hot_bath([true, false, true, false, false])
+                .build();
+            assert_eq!(world.gradient(BathId::Hot), 1);
+            let world = World::builder().hot_bath([false, true, false]).build();
+            assert_eq!(world.gradient(BathId::Hot), 0);
+        }
+    }
+
+    mod contents_eq {
+        use crate::*;
+
+        #[test]
+        fn test_differs_only_in_t() {
+            let start = World::classic([true, false], [true, true, false], [false, true]);
+            let mut later = start.clone();
+            later.t = 12;
+            assert!(later.contents_eq(&start));
+            assert_ne!(later, start);
+        }
+
+        #[test]
+        fn test_differs_only_in_contents() {
+            let start = World::classic([true, false], [true, true, false], [false, true]);
+            let mut other = start.clone();
+            other.swap_cells((BathId::Battery, 0), (BathId::Cold, 0));
+            assert_eq!(other.t, start.t);
+            assert!(!other.contents_eq(&start));
+
+            let mut extra = start.clone();
+            extra.add_subsystem("warm", [false]);
+            assert!(!extra.contents_eq(&start));
+        }
+    }
+
+    mod subsystem_pair_mut {
+        use crate::*;
+
+        #[test]
+        fn test_disjoint() {
+            let mut world = World::builder()
+                .battery([true, false])
+                .hot_bath([false, false, true])
+                .cold_bath([true])
+                .build();
+            let (hot, battery) = world.subsystem_pair_mut(BathId::Hot, BathId::Battery);
+            hot.swap_with(0, battery, 0);
+            assert_eq!(world.battery(), &BitBath::from([false, false]));
+            assert_eq!(world.hot_bath(), &BitBath::from([true, false, true]));
+
+            let (battery, cold) = world.subsystem_pair_mut(BathId::Battery, BathId::Cold);
+            assert_eq!((battery.len(), cold.len()), (2, 1));
+        }
+
+        #[test]
+        #[should_panic(expected = "can't borrow cold mutably twice")]
+        fn test_aliased() {
+            let mut world = World::builder()
+                .battery([true, false])
+                .hot_bath([false, false, true])
+                .cold_bath([true])
+                .build();
+            world.subsystem_pair_mut(BathId::Cold, BathId::Cold);
+        }
+    }
+
+    mod energy {
+        use crate::*;
+
+        #[test]
+        fn test_counts() {
+            let world = World::builder()
+                .battery([false, true, false, false])
+                .hot_bath([true, true, false, true, false])
+                .cold_bath([false, false, true])
+                .build();
+            assert_eq!(world.battery_energy(), 1);
+            assert_eq!(world.hot_bath_energy(), 3);
+            assert_eq!(world.cold_bath_energy(), 1);
+            assert_eq!(world.total_energy(), 5);
+        }
+
+        #[test]
+        fn test_conserved() {
+            let mut world = World::builder()
+                .battery([false, true, false, false])
+                .hot_bath([true, true, false, true, false])
+                .cold_bath([false, false, true])
+                .build();
+            let rules: Vec<Box<dyn Rule>> = vec![
+                Box::new(
+                    Permute::new(vec![3, 2, 1, 0], vec![1, 2, 3, 4, 0], vec![2, 0, 1]).unwrap(),
+                ),
+                Box::new(CondSwap),
+                Box::new(ProbeAndSwap::default()),
+            ];
+            for _ in 0..20 {
+                for rule in &rules {
+                    rule.step(&mut world);
+                    assert_eq!(world.total_energy(), 5);
+                }
+                world.t += 1;
+            }
+        }
+    }
+
+    mod reversibility {
+        use super::sample_world;
+        use crate::*;
+
+        #[test]
+        fn test_cond_swap() {
+            assert_reversible(&CondSwap, &sample_world(3, 10), 50);
+        }
+
+        #[test]
+        fn test_permute() {
+            let permute = Permute::new(
+                vec![1, 2, 3, 0],
+                generate_random_permutation(10, 1),
+                generate_random_permutation(6, 2),
+            )
+            .unwrap();
+            assert_reversible(&permute, &sample_world(3, 10), 50);
+        }
+
+        #[test]
+        fn test_composed() {
+            let rules: Vec<Box<dyn Rule>> = vec![
+                Box::new(CondSwap),
+                Box::new(ProbeAndSwap::default()),
+                Box::new(WeirdPermute::new(5)),
+            ];
+            assert_reversible(&rules, &sample_world(3, 10), 50);
+        }
+
+        #[test]
+        fn test_extra_baths() {
+            let mut world = sample_world(3, 10);
+            world.add_subsystem("warm", [true, false, true, false, false]);
+            let rules: Vec<Box<dyn Rule>> = vec![
+                Box::new(CondSwap),
+                Box::new(
+                    Permute::from_permutations(vec![
+                        vec![0, 1, 2, 3],
+                        generate_random_permutation(10, 3),
+                        generate_random_permutation(6, 4),
+                        vec![4, 0, 1, 2, 3],
+                    ])
+                    .unwrap(),
+                ),
+                Box::new(WeirdPermute::new(5)),
+            ];
+            assert_reversible(&rules, &world, 50);
+        }
+    }
+
+    mod world_view {
+        use crate::*;
+
+        #[test]
+        fn test_cross_bath_swaps() {
+            let mut world = World::classic([true, false], [false, true, true], [false]);
+            let view = world.view();
+            view.battery.swap_with(0, view.cold, 0);
+            view.hot.swap_with(2, view.battery, 1);
+            assert_eq!(
+                world,
+                World::classic([false, true], [false, true, false], [true])
+            );
+        }
+
+        #[test]
+        fn test_cond_swap_matches_swap_cells() {
+            for bits in 0..16u8 {
+                let bit = |i: u8| bits >> i & 1 == 1;
+                let world = World::classic([bit(0), true], [bit(1), bit(2)], [bit(3)]);
+                let mut expected = world.clone();
+                if [bit(1), bit(2), bit(3), bit(0)] == [true, true, false, false]
+                    || [bit(1), bit(2), bit(3), bit(0)] == [false, false, true, true]
+                {
+                    expected.swap_cells((BathId::Hot, 0), (BathId::Cold, 0));
+                    expected.swap_cells((BathId::Hot, 1), (BathId::Battery, 0));
+                }
+                let mut world = world;
+                CondSwap.apply(world.view());
+                assert_eq!(world, expected);
+            }
+        }
+
+        #[test]
+        fn test_extra_baths_untouched() {
+            let mut world = World::classic([false], [true, true], [false]);
+            world.add_subsystem("warm", [true]);
+            CondSwap.step(&mut world);
+            assert_eq!(world.energy(BathId::Extra(0)), 1);
+            assert_eq!(world.battery_energy(), 1);
+            assert_eq!(world.cold_bath_energy(), 1);
+        }
+    }
+
+    mod probe_and_swap {
+        use crate::*;
+
+        fn hardcoded(world: &mut World) {
+            if world.hot_bath()[0] {
+                world.swap_cells((BathId::Battery, 1), (BathId::Hot, 1));
+            }
+        }
+
+        #[test]
+        fn test_matches_hardcoded() {
+            let rule = ProbeAndSwap::new(0, 1, 1);
+            assert_eq!(rule, ProbeAndSwap::default());
+            for bits in 0..16u8 {
+                let bit = |i: u8| bits >> i & 1 == 1;
+                let world = World::classic([false, bit(0)], [bit(1), bit(2), bit(3)], []);
+                let (mut a, mut b) = (world.clone(), world);
+                rule.step(&mut a);
+                hardcoded(&mut b);
+                assert_eq!(a, b);
+            }
+        }
+
+        #[test]
+        fn test_other_indices() {
+            let mut world = World::classic([false, false, false], [false, true, false, true], []);
+            ProbeAndSwap::new(1, 2, 3).step(&mut world);
+            assert_eq!(world.battery().to_vec(), vec![false, false, true]);
+            assert_eq!(world.hot_bath().to_vec(), vec![false, true, false, false]);
+            assert_reversible(&ProbeAndSwap::new(1, 2, 3), &world, 5);
+        }
+
+        #[test]
+        #[should_panic(expected = "out of range")]
+        fn test_short_bath() {
+            let mut world = World::classic([false; 2], [true], []);
+            ProbeAndSwap::default().step(&mut world);
+        }
+    }
+
+    mod footprint {
+        use crate::*;
+
+        #[test]
+        fn test_cond_swap() {
+            let footprint = CondSwap.footprint();
+            assert_eq!(
+                footprint.cells().collect::<Vec<_>>(),
+                vec![
+                    (BathId::Battery, 0),
+                    (BathId::Hot, 0),
+                    (BathId::Hot, 1),
+                    (BathId::Cold, 0),
+                ]
+            );
+            assert_eq!(footprint.subsystems().count(), 0);
+        }
+
+        #[test]
+        fn test_probe_and_swap() {
+            let footprint = ProbeAndSwap::new(3, 0, 5).footprint();
+            assert_eq!(
+                footprint,
+                Footprint::from_cells([(BathId::Hot, 3), (BathId::Battery, 0), (BathId::Hot, 5)])
+            );
+            // The probe and the swapped cell can share an index across baths.
+            assert_eq!(ProbeAndSwap::new(1, 1, 2).footprint().cells().count(), 3);
+        }
+
+        #[test]
+        fn test_whole_baths() {
+            assert_eq!(
+                CyclicShift {
+                    subsystem: BathId::Cold,
+                    offset: 2,
+                }
+                .footprint(),
+                Footprint::from_subsystems([BathId::Cold])
+            );
+            assert!(WeirdPermute::new(1).footprint().is_all());
+            assert!(NoOp.footprint().is_empty());
+        }
+
+        #[test]
+        fn test_composite() {
+            let rules: Vec<Box<dyn Rule>> = vec![
+                Box::new(CondSwap),
+                Box::new(Repeat {
+                    inner: Box::new(ReflectBath {
+                        subsystem: BathId::Extra(0),
+                    }),
+                    times: 2,
+                }),
+            ];
+            assert_eq!(
+                rules.footprint(),
+                CondSwap
+                    .footprint()
+                    .union(&Footprint::from_subsystems([BathId::Extra(0)]))
+            );
+            assert_eq!(CondSwap.then(NoOp).footprint(), CondSwap.footprint());
+        }
+    }
+
+    mod controlled_swap {
+        use crate::*;
+
+        #[test]
+        fn test_reproduces_cond_swap_firing() {
+            // In the state where CondSwap moves a hot quantum to each of the
+            // cold bath and the battery, two Fredkin gates do the same.
+            let gates = [
+                ControlledSwap::new((BathId::Hot, 1), (BathId::Hot, 0), (BathId::Cold, 0)),
+                ControlledSwap::new((BathId::Cold, 0), (BathId::Hot, 1), (BathId::Battery, 0)),
+            ];
+            let world = World::classic([false, true], [true, true, false], [false, true]);
+            let (mut a, mut b) = (world.clone(), world.clone());
+            CondSwap.step(&mut a);
+            for gate in &gates {
+                gate.step(&mut b);
+            }
+            assert_ne!(a, world);
+            assert_eq!(a, b);
+        }
+
+        #[test]
+        fn test_twice_is_identity() {
+            let gate =
+                ControlledSwap::new((BathId::Battery, 0), (BathId::Hot, 2), (BathId::Cold, 1));
+            for bits in 0..8u8 {
+                let bit = |i: u8| bits >> i & 1 == 1;
+                let world = World::classic([bit(0)], [false, false, bit(1)], [false, bit(2)]);
+                let mut stepped = world.clone();
+                gate.step(&mut stepped);
+                if bit(0) && bit(1) != bit(2) {
+                    assert_ne!(stepped, world);
+                }
+                gate.step(&mut stepped);
+                assert_eq!(stepped, world);
+                assert_reversible(&gate, &world, 3);
+            }
+        }
+
+        #[test]
+        #[should_panic(expected = "irreversible")]
+        fn test_control_swapped() {
+            ControlledSwap::new((BathId::Hot, 0), (BathId::Hot, 0), (BathId::Cold, 0));
+        }
+    }
+
+    mod cold_couple {
+        use crate::*;
+
+        #[test]
+        fn test_moves_energy() {
+            let rule = ColdCouple::new(0, 1, 2);
+            let mut world = World::classic([false, false], [], [true, false, true]);
+            rule.step(&mut world);
+            assert_eq!(world.battery_energy(), 1);
+            assert_eq!(world.cold_bath(), &BitBath::from([true, false, false]));
+            rule.step(&mut world);
+            assert_eq!(world.battery_energy(), 0);
+            assert_eq!(world.cold_bath_energy(), 2);
+
+            let mut unprobed = World::classic([true, true], [], [false, false, false]);
+            rule.step(&mut unprobed);
+            assert_eq!(unprobed.battery_energy(), 2);
+        }
+
+        #[test]
+        fn test_reversible() {
+            for bits in 0..16u8 {
+                let bit = |i: u8| bits >> i & 1 == 1;
+                let world = World::classic([bit(0), false], [true], [bit(1), bit(2), bit(3)]);
+                assert_reversible(&ColdCouple::new(1, 0, 2), &world, 5);
+            }
+        }
+
+        #[test]
+        #[should_panic(expected = "irreversible")]
+        fn test_probe_swapped() {
+            ColdCouple::new(1, 0, 1);
+        }
+    }
+
+    mod c_not {
+        use crate::*;
+
+        #[test]
+        fn test_truth_table() {
+            let gate = CNot::new((BathId::Hot, 1), (BathId::Battery, 0));
+            for (control, target) in [(false, false), (false, true), (true, false), (true, true)] {
+                let world = World::classic([target, true], [false, control], [true]);
+                let mut stepped = world.clone();
+                gate.step(&mut stepped);
+                assert_eq!(stepped.battery()[0], target ^ control);
+                assert_eq!(stepped.hot_bath(), world.hot_bath());
+                assert!(stepped.battery()[1]);
+                gate.step(&mut stepped);
+                assert_eq!(stepped, world);
+                assert_reversible(&gate, &world, 3);
+            }
+        }
+
+        #[test]
+        #[should_panic(expected = "irreversible")]
+        fn test_self_target() {
+            CNot::new((BathId::Cold, 2), (BathId::Cold, 2));
+        }
+    }
+
+    mod stochastic_swap {
+        use crate::*;
+
+        fn swap(probability: f64) -> StochasticSwap {
+            StochasticSwap::new((BathId::Battery, 0), (BathId::Cold, 1), probability, 17)
+        }
+
+        #[test]
+        fn test_round_trip() {
+            let rule = swap(0.4);
+            for t in -20..20 {
+                let start = World::builder()
+                    .t(t)
+                    .battery([true, false])
+                    .hot_bath([false, true, true])
+                    .cold_bath([false, false])
+                    .build();
+                let mut world = start.clone();
+                rule.step(&mut world);
+                if rule.fires(t) {
+                    assert_eq!(world.battery_energy(), 0);
+                    assert_eq!(world.cold_bath_energy(), 1);
+                } else {
+                    assert_eq!(world, start);
+                }
+                world.t += 1;
+                rule.inverse().step(&mut world);
+                world.t -= 1;
+                assert_eq!(world, start);
+                assert_reversible(&rule, &start, 25);
+            }
+        }
+
+        #[test]
+        fn test_probability() {
+            let rule = swap(0.3);
+            let fired = (0..10_000).filter(|&t| rule.fires(t)).count();
+            assert!((2_800..3_200).contains(&fired), "fired {fired} times");
+            assert!((-100..100).all(|t| !swap(0.0).fires(t)));
+            assert!((-100..100).all(|t| swap(1.0).fires(t)));
+            assert_eq!(rule.seed(), Some(17));
+        }
+
+        #[test]
+        #[should_panic(expected = "probability must be in [0, 1]")]
+        fn test_bad_probability() {
+            swap(1.5);
+        }
+    }
+
+    mod conditional_bath_swap {
+        use crate::*;
+
+        #[test]
+        fn test_toggle() {
+            let rule = ConditionalBathSwap::new((BathId::Battery, 0));
+            for control in [false, true] {
+                let world =
+                    World::classic([control, false], [true, true, false], [false, false, true]);
+                let mut stepped = world.clone();
+                rule.step(&mut stepped);
+                if control {
+                    assert_eq!(stepped.hot_bath(), world.cold_bath());
+                    assert_eq!(stepped.cold_bath(), world.hot_bath());
+                } else {
+                    assert_eq!(stepped, world);
+                }
+                assert_eq!(stepped.battery(), world.battery());
+                rule.inverse().step(&mut stepped);
+                assert_eq!(stepped, world);
+                assert_reversible(&rule, &world, 4);
+            }
+        }
+
+        #[test]
+        fn test_validate() {
+            let rule = ConditionalBathSwap::new((BathId::Battery, 0));
+            assert_eq!(
+                rule.validate(&World::classic([true], [true, false], [false])),
+                Err(RuleError::WrongLength {
+                    rule: "ConditionalBathSwap",
+                    subsystem: BathId::Cold,
+                    expected: 2,
+                    len: 1,
+                })
+            );
+            assert!(rule.validate(&World::new(1, 5)).is_ok());
+        }
+
+        #[test]
+        #[should_panic(expected = "irreversible")]
+        fn test_control_in_swapped_bath() {
+            ConditionalBathSwap::new((BathId::Cold, 0));
+        }
+    }
+
+    mod validate {
+        use crate::*;
+
+        #[test]
+        fn test_default_rules_fit() {
+            let world = World::new(1, 2);
+            assert_eq!(default_rules(0).validate(&world), Ok(()));
+        }
+
+        #[test]
+        fn test_too_small() {
+            let world = World::classic([false], [true], [false]);
+            assert_eq!(
+                CondSwap.validate(&world),
+                Err(RuleError::OutOfRange {
+                    rule: "CondSwap",
+                    subsystem: BathId::Hot,
+                    index: 1,
+                    len: 1,
+                })
+            );
+            let err = ProbeAndSwap::default().validate(&world).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "ProbeAndSwap uses battery cell 1, which is out of range for length 1"
+            );
+        }
+
+        #[test]
+        fn test_composites() {
+            let world = World::classic([false; 3], [true; 3], []);
+            let shift = CyclicShift {
+                subsystem: BathId::Extra(0),
+                offset: 1,
+            };
+            let rules: Vec<Box<dyn Rule>> = vec![
+                Box::new(ProbeAndSwap::default()),
+                Box::new(Repeat {
+                    inner: NoOp.then(shift),
+                    times: 2,
+                }),
+            ];
+            assert_eq!(
+                rules.validate(&world),
+                Err(RuleError::MissingSubsystem {
+                    rule: "CyclicShift",
+                    subsystem: BathId::Extra(0),
+                })
+            );
+            let permute = Permute::new(vec![0, 1], vec![], vec![]).unwrap();
+            assert_eq!(
+                permute.validate(&world).unwrap_err().to_string(),
+                "Permute has a battery permutation of length 2, expected 3"
+            );
+        }
+    }
+
+    mod no_op {
+        use crate::*;
+
+        #[test]
+        fn test_unchanged() {
+            let world = World::builder()
+                .t(-3)
+                .battery([true, false])
+                .hot_bath_half_full(8)
+                .build();
+            let mut stepped = world.clone();
+            for _ in 0..100 {
+                NoOp.step(&mut stepped);
+            }
+            assert_eq!(stepped, world);
+            assert_reversible(&NoOp, &world, 100);
+        }
+    }
+
+    mod block_rule {
+        use crate::*;
+
+        #[test]
+        fn test_bad_table() {
+            assert_eq!(
+                BlockRule::new(BathId::Hot, 2, vec![0, 1, 2]).unwrap_err(),
+                PermuteError::WrongLength {
+                    subsystem: BathId::Hot,
+                    expected: 4,
+                    len: 3,
+                }
+            );
+            assert!(BlockRule::new(BathId::Hot, 2, vec![0, 1, 1, 3]).is_err());
+        }
+
+        #[test]
+        fn test_offset_alternates() {
+            // Swapping the two cells of each pair walks a lone particle
+            // rightward one cell per step as the pairing shifts.
+            let rule = BlockRule::new(BathId::Cold, 2, vec![0, 2, 1, 3]).unwrap();
+            let mut world = World::classic([], [], [true, false, false, false, false]);
+            for expected in 1..5 {
+                rule.step(&mut world);
+                world.t += 1;
+                let mut bits = [false; 5];
+                bits[expected] = true;
+                assert_eq!(world.cold_bath(), &BitBath::from(bits));
+            }
+        }
+
+        #[test]
+        fn test_reversible() {
+            let table = generate_random_permutation(8, 3);
+            let rule = BlockRule::new(BathId::Hot, 3, table).unwrap();
+            let world = World::builder()
+                .t(-3)
+                .hot_bath([
+                    true, true, false, true, false, false, true, false, true, true,
+                ])
+                .build();
+            assert_reversible(&rule, &world, 11);
+            assert_reversible(&rule, &World { t: 4, ..world }, 10);
+        }
+    }
+
+    mod clone_box {
+        use crate::*;
+
+        #[test]
+        fn test_cloned_rule_sets_agree() {
+            let rules: Vec<Box<dyn Rule>> = vec![
+                Box::new(CondSwap),
+                Box::new(ProbeAndSwap::default()),
+                Box::new(Repeat {
+                    inner: Box::new(WeirdPermute::new(3)),
+                    times: 2,
+                }),
+            ];
+            let cloned = rules.clone();
+            let start = World::from_config(&SimConfig {
+                battery_size: 4,
+                bath_size: 16,
+                n_steps: 0,
+                ..SimConfig::default()
+            });
+            let (mut a, mut b) = (start.clone(), start);
+            for _ in 0..30 {
+                rules.step(&mut a);
+                a.t += 1;
+                cloned.step(&mut b);
+                b.t += 1;
+            }
+            assert_eq!(a, b);
+        }
+    }
+
+    mod cyclic_shift {
+        use super::sample_world;
+        use crate::*;
+
+        fn shift(offset: isize) -> CyclicShift {
+            CyclicShift {
+                subsystem: BathId::Hot,
+                offset,
+            }
+        }
+
+        #[test]
+        fn test_direction() {
+            let mut world = sample_world(0, 7);
+            shift(1).step(&mut world);
+            assert_eq!(
+                world.hot_bath(),
+                &BitBath::from([false, true, true, true, true, false, false])
+            );
+            assert_eq!(world.battery(), &BitBath::from([false, true, false, true]));
+            shift(-1).step(&mut world);
+            assert_eq!(world, sample_world(0, 7));
+        }
+
+        #[test]
+        fn test_complementary_shifts() {
+            let start = sample_world(0, 7);
+            for k in [0, 1, 3, 6, 7, 15, -2, -16] {
+                let mut world = start.clone();
+                shift(k).step(&mut world);
+                shift(7 - k).step(&mut world);
+                assert_eq!(world, start, "k={k}");
+                assert_reversible(&shift(k), &start, 3);
+            }
+        }
+
+        #[test]
+        fn test_equivalent_offsets() {
+            let start = sample_world(0, 7);
+            let (mut a, mut b, mut c) = (start.clone(), start.clone(), start);
+            shift(3).step(&mut a);
+            shift(10).step(&mut b);
+            shift(-4).step(&mut c);
+            assert_eq!(a, b);
+            assert_eq!(a, c);
+        }
+
+        #[test]
+        fn test_extreme_offsets() {
+            let start = sample_world(0, 7);
+            for k in [isize::MIN, isize::MIN + 1, isize::MAX] {
+                assert_reversible(&shift(k), &start, 3);
+                let rules = parse_rules(&format!("shift(hot,{k})")).unwrap();
+                assert_reversible(&rules, &start, 3);
+            }
+        }
+    }
+
+    mod reflect_bath {
+        use crate::*;
+
+        fn reflect(world: &World, id: BathId) -> World {
+            let mut world = world.clone();
+            ReflectBath { subsystem: id }.step(&mut world);
+            world
+        }
+
+        #[test]
+        fn test_palindrome() {
+            let world = World::classic(
+                [true, true],
+                [true, false, false, true],
+                [false, true, false],
+            );
+            for id in world.ids().collect::<Vec<_>>() {
+                assert_eq!(reflect(&world, id), world);
+            }
+        }
+
+        #[test]
+        fn test_non_palindrome() {
+            let world = World::classic(
+                [true, false],
+                [true, true, false, true, false],
+                [true, false, false, false],
+            );
+            let hot = reflect(&world, BathId::Hot);
+            assert_eq!(
+                hot.hot_bath(),
+                &BitBath::from([false, true, false, true, true])
+            );
+            assert_eq!(hot.battery(), world.battery());
+            assert_eq!(hot.cold_bath(), world.cold_bath());
+
+            let cold = reflect(&world, BathId::Cold);
+            assert_eq!(
+                cold.cold_bath(),
+                &BitBath::from([false, false, false, true])
+            );
+            assert_eq!(cold.hot_bath(), world.hot_bath());
+
+            for id in world.ids().collect::<Vec<_>>() {
+                assert_eq!(reflect(&reflect(&world, id), id), world);
+                assert_reversible(&ReflectBath { subsystem: id }, &world, 3);
+            }
+        }
+    }
+
+    mod then {
+        use super::sample_world;
+        use crate::*;
+
+        #[test]
+        fn test_matches_vec() {
+            let chained = CondSwap
+                .then(ReflectBath {
+                    subsystem: BathId::Hot,
+                })
+                .then(WeirdPermute::new(4));
+            let vec: Vec<Box<dyn Rule>> = vec![
+                Box::new(CondSwap),
+                Box::new(ReflectBath {
+                    subsystem: BathId::Hot,
+                }),
+                Box::new(WeirdPermute::new(4)),
+            ];
+            assert_eq!(chained.seed(), Some(4));
+            let (mut a, mut b) = (sample_world(2, 9), sample_world(2, 9));
+            for _ in 0..30 {
+                chained.step(&mut a);
+                a.t += 1;
+                vec.step(&mut b);
+                b.t += 1;
+                assert_eq!(a, b);
+            }
+            let (inv_chained, inv_vec) = (chained.inverse(), vec.inverse());
+            for _ in 0..30 {
+                inv_chained.step(&mut a);
+                a.t -= 1;
+                inv_vec.step(&mut b);
+                b.t -= 1;
+                assert_eq!(a, b);
+            }
+            assert_eq!(a, sample_world(2, 9));
+        }
+
+        #[test]
+        fn test_order() {
+            // Shifting then reflecting differs from reflecting then shifting.
+            let shift = CyclicShift {
+                subsystem: BathId::Hot,
+                offset: 1,
+            };
+            let reflect = ReflectBath {
+                subsystem: BathId::Hot,
+            };
+            let (mut a, mut b) = (sample_world(2, 9), sample_world(2, 9));
+            shift.then(reflect).step(&mut a);
+            shift.step(&mut b);
+            reflect.step(&mut b);
+            assert_eq!(a, b);
+            let mut c = sample_world(2, 9);
+            reflect.then(shift).step(&mut c);
+            assert_ne!(a, c);
+            assert_reversible(shift.then(reflect).as_ref(), &sample_world(2, 9), 5);
+        }
+    }
+
+    mod odd_even_step {
+        use crate::*;
+
+        fn world(hot: impl Into<BitBath>, record: usize) -> World {
+            World::builder()
+                .battery([true, false])
+                .hot_bath(hot)
+                .cold_bath([false, true])
+                .extra("record", BitBath::new(record))
+                .build()
+        }
+
+        fn rule() -> OddEvenStep {
+            OddEvenStep::new(BathId::Hot, BathId::Extra(0))
+        }
+
+        #[test]
+        fn test_sorts() {
+            let start = world([true, false, true, true, false, false, true], 49);
+            let mut world = start.clone();
+            for _ in 0..7 {
+                rule().step(&mut world);
+                world.t += 1;
+            }
+            assert_eq!(
+                world.hot_bath(),
+                &BitBath::from([false, false, false, true, true, true, true])
+            );
+            assert_eq!(world.battery(), start.battery());
+            assert_eq!(world.cold_bath(), start.cold_bath());
+            // One record per swap, and each swap fixes one of the seven
+            // (occupied, empty) pairs out of order.
+            assert_eq!(world.subsystem(BathId::Extra(0)).count_ones(), 7);
+        }
+
+        #[test]
+        fn test_inverse() {
+            let start = world([true, true, true, false, false, false], 36);
+            let mut world = start.clone();
+            for _ in 0..6 {
+                rule().step(&mut world);
+                world.t += 1;
+            }
+            assert_eq!(
+                world.hot_bath(),
+                &BitBath::from([false, false, false, true, true, true])
+            );
+            let inverse = rule().inverse();
+            for _ in 0..6 {
+                inverse.step(&mut world);
+                world.t -= 1;
+            }
+            assert_eq!(world, start);
+        }
+
+        #[test]
+        fn test_reversible() {
+            // Past the ancilla's rows, and from arbitrary records.
+            for record in [6, 13, 24] {
+                let mut world = world([false, false, true, true, false, true], record);
+                assert_reversible(&rule(), &world, 40);
+                for i in (0..record).step_by(3) {
+                    world.subsystem_mut(BathId::Extra(0)).set(i, true);
+                }
+                world.t = -5;
+                assert_reversible(&rule(), &world, 40);
+            }
+        }
+
+        #[test]
+        fn test_validate() {
+            assert_eq!(rule().validate(&world([true, false], 2)), Ok(()));
+            assert_eq!(
+                rule().validate(&world([true, false, true], 2)),
+                Err(RuleError::Ancilla {
+                    rule: "OddEvenStep",
+                    ancilla: BathId::Extra(0),
+                    needed: 3,
+                    len: 2,
+                })
+            );
+            assert_eq!(
+                OddEvenStep::new(BathId::Hot, BathId::Hot).validate(&world([true, false], 2)),
+                Err(RuleError::Ancilla {
+                    rule: "OddEvenStep",
+                    ancilla: BathId::Hot,
+                    needed: 2,
+                    len: 2,
+                })
+            );
+            assert_eq!(
+                OddEvenStep::new(BathId::Hot, BathId::Extra(1)).validate(&world([true], 1)),
+                Err(RuleError::MissingSubsystem {
+                    rule: "OddEvenStep",
+                    subsystem: BathId::Extra(1),
+                })
+            );
+        }
+    }
+
+    mod conditional {
+        use crate::*;
+
+        fn rule(threshold: usize) -> Conditional {
+            Conditional {
+                predicate: Rc::new(move |w: &World| w.hot_bath_energy() >= threshold),
+                inner: Box::new(WeirdPermute::new(8)),
+            }
+        }
+
+        #[test]
+        fn test_threshold() {
+            let world = World::from_config(&SimConfig {
+                battery_size: 4,
+                bath_size: 20,
+                n_steps: 0,
+                ..SimConfig::default()
+            });
+            let mut below = world.clone();
+            rule(11).step(&mut below);
+            assert_eq!(below, world);
+
+            let mut above = world.clone();
+            rule(10).step(&mut above);
+            assert_ne!(above, world);
+
+            assert_reversible(&rule(10), &world, 30);
+        }
+    }
+
+    mod scheduled {
+        use super::sample_world;
+        use crate::*;
+
+        fn shift(subsystem: BathId) -> Box<dyn Rule> {
+            Box::new(CyclicShift {
+                subsystem,
+                offset: 1,
+            })
+        }
+
+        #[test]
+        fn test_dispatch() {
+            let start = World::builder()
+                .battery([true, false, false])
+                .hot_bath([true, false, false, false])
+                .cold_bath([true, false, false, false, false])
+                .build();
+            let rule = Scheduled::new(vec![
+                (0..2, shift(BathId::Hot)),
+                (2..3, shift(BathId::Cold)),
+            ]);
+            let mut world = start.clone();
+            for _ in 0..5 {
+                rule.step(&mut world);
+                world.t += 1;
+            }
+            assert_eq!(
+                world.hot_bath(),
+                &BitBath::from([false, false, true, false])
+            );
+            assert_eq!(
+                world.cold_bath(),
+                &BitBath::from([false, true, false, false, false])
+            );
+            assert_eq!(world.battery(), start.battery());
+        }
+
+        #[test]
+        fn test_reversible_across_boundary() {
+            let rule = Scheduled::new(vec![
+                (-3..4, Box::new(WeirdPermute::new(2))),
+                (4..7, shift(BathId::Battery).then(CondSwap)),
+                (6..20, shift(BathId::Hot)),
+            ]);
+            assert_eq!(rule.seed(), Some(2));
+            assert_reversible(&rule, &sample_world(1, 4), 10);
+            assert_reversible(&rule, &sample_world(-5, 4), 30);
+        }
+    }
+
+    mod repeat {
+        use super::sample_world;
+        use crate::*;
+
+        #[test]
+        fn test_matches_manual_steps() {
+            let mut repeated = sample_world(0, 4);
+            Repeat {
+                inner: Box::new(CondSwap),
+                times: 3,
+            }
+            .step(&mut repeated);
+            let mut manual = sample_world(0, 4);
+            for _ in 0..3 {
+                CondSwap.step(&mut manual);
+            }
+            assert_eq!(repeated, manual);
+        }
+
+        #[test]
+        fn test_rule_set() {
+            let rules = RuleSet::new()
+                .push(CondSwap)
+                .repeat(WeirdPermute::new(2), 3)
+                .push(ReflectBath {
+                    subsystem: BathId::Hot,
+                });
+            let inverse = rules.inverse();
+            let built = rules.build();
+            assert_eq!(built.len(), 3);
+            let manual: Vec<Box<dyn Rule>> = vec![
+                Box::new(CondSwap),
+                Box::new(WeirdPermute::new(2)),
+                Box::new(WeirdPermute::new(2)),
+                Box::new(WeirdPermute::new(2)),
+                Box::new(ReflectBath {
+                    subsystem: BathId::Hot,
+                }),
+            ];
+            let (mut a, mut b) = (sample_world(0, 4), sample_world(0, 4));
+            for _ in 0..10 {
+                built.step(&mut a);
+                a.t += 1;
+                manual.step(&mut b);
+                b.t += 1;
+                assert_eq!(a, b);
+            }
+            for _ in 0..10 {
+                inverse.step(&mut a);
+                a.t -= 1;
+            }
+            assert_eq!(a, sample_world(0, 4));
+            assert!(RuleSet::new().build().is_empty());
+        }
+
+        #[test]
+        fn test_reversible_time_dependent() {
+            let rule = Repeat {
+                inner: Box::new(WeirdPermute::new(2)),
+                times: 4,
+            };
+            assert_reversible(&rule, &sample_world(0, 4), 20);
+        }
+    }
+
+    mod permute_new {
+        use crate::*;
+
+        #[test]
+        fn test_valid() {
+            assert!(Permute::new(vec![1, 0], vec![], vec![2, 0, 1]).is_ok());
+        }
+
+        #[test]
+        fn test_out_of_range() {
+            assert_eq!(
+                Permute::new(vec![1, 0], vec![0, 3, 1], vec![]).unwrap_err(),
+                PermuteError::OutOfRange {
+                    subsystem: BathId::Hot,
+                    index: 3,
+                    len: 3,
+                }
+            );
+        }
+
+        #[test]
+        fn test_duplicate() {
+            assert_eq!(
+                Permute::new(vec![0, 1], vec![], vec![1, 1, 0]).unwrap_err(),
+                PermuteError::Duplicate {
+                    subsystem: BathId::Cold,
+                    index: 1,
+                }
+            );
+        }
+    }
+
+    mod permute_inverse {
+        use crate::*;
+
+        #[test]
+        fn test_unequal_lengths() {
+            let start = World::classic(
+                [true, false, false],
+                [true, true, false, true, false, false, true],
+                [false, true, true, false, false],
+            );
+            let permute = Permute::new(
+                vec![2, 0, 1],
+                // Cycles (0 1)(2 3 4)(5)(6).
+                vec![1, 0, 3, 4, 2, 5, 6],
+                vec![4, 3, 2, 1, 0],
+            )
+            .unwrap();
+            let mut world = start.clone();
+            permute.step(&mut world);
+            assert_ne!(world.battery(), start.battery());
+            assert_ne!(world.hot_bath(), start.hot_bath());
+            assert_ne!(world.cold_bath(), start.cold_bath());
+            permute.inverse().step(&mut world);
+            assert_eq!(world, start);
+
+            permute.inverse().step(&mut world);
+            permute.step(&mut world);
+            assert_eq!(world, start);
+        }
+
+        #[test]
+        fn test_independent_subsystems() {
+            let start = World::classic(
+                [true, false, false],
+                [true, true, false, true, false, false, true],
+                [false, true, true, false, false],
+            );
+            let permute = Permute::new(
+                vec![0, 1, 2],
+                vec![1, 2, 0, 4, 3, 5, 6],
+                vec![0, 1, 2, 3, 4],
+            )
+            .unwrap();
+            let mut world = start.clone();
+            permute.inverse().step(&mut world);
+            assert_ne!(world.hot_bath(), start.hot_bath());
+            assert_eq!(world.battery(), start.battery());
+            assert_eq!(world.cold_bath(), start.cold_bath());
+            permute.step(&mut world);
+            assert_eq!(world, start);
+        }
+    }
+
+    mod permute_compose {
+        use crate::*;
+
+        #[test]
+        fn test_matches_double_application() {
+            let start = World::builder()
+                .battery([true, false, false, true])
+                .hot_bath_half_full(9)
+                .cold_bath([false, true, true, false, false])
+                .extra("warm", [true, false, true])
+                .build();
+            let first = Permute::new(
+                vec![2, 0, 3, 1],
+                generate_random_permutation(9, 1),
+                generate_random_permutation(5, 2),
+            )
+            .unwrap();
+            let second = Permute::from_permutations(vec![
+                vec![3, 2, 1, 0],
+                generate_random_permutation(9, 3),
+                generate_random_permutation(5, 4),
+                vec![1, 2, 0],
+            ])
+            .unwrap();
+            let (mut manual, mut composed) = (start.clone(), start.clone());
+            first.step(&mut manual);
+            second.step(&mut manual);
+            first.compose(&second).unwrap().step(&mut composed);
+            assert_eq!(composed, manual);
+            assert_ne!(composed, start);
+        }
+
+        #[test]
+        fn test_compose_permutations() {
+            let (first, second) = (vec![1, 2, 0, 3], vec![3, 1, 0, 2]);
+            let mut manual = vec!['a', 'b', 'c', 'd'];
+            permute(&first, &mut manual);
+            permute(&second, &mut manual);
+            let mut composed = vec!['a', 'b', 'c', 'd'];
+            permute(&compose_permutations(&first, &second), &mut composed);
+            assert_eq!(composed, manual);
+        }
+
+        #[test]
+        fn test_length_mismatch() {
+            let a = Permute::new(vec![0, 1], vec![], vec![]).unwrap();
+            let b = Permute::new(vec![0, 1, 2], vec![], vec![]).unwrap();
+            assert_eq!(
+                a.compose(&b).unwrap_err(),
+                PermuteError::WrongLength {
+                    subsystem: BathId::Battery,
+                    expected: 2,
+                    len: 3,
+                }
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    mod serde {
+        use crate::*;
+
+        #[test]
+        fn test_json_format() {
+            let world = World::builder()
+                .t(-4)
+                .battery([true])
+                .hot_bath([false, true])
+                .build();
+            assert_eq!(
+                serde_json::to_string(&world).unwrap(),
+                concat!(
+                    r#"{"t":-4,"subsystems":["#,
+                    r#"{"name":"battery","cells":[true]},"#,
+                    r#"{"name":"hot","cells":[false,true]},"#,
+                    r#"{"name":"cold","cells":[]}]}"#
+                )
+            );
+        }
+
+        #[test]
+        fn test_round_trip() {
+            let world = World::builder()
+                .t(12)
+                .battery([false, true, true])
+                .hot_bath_half_full(70)
+                .cold_bath([true, false, false, false, true])
+                .build();
+            let json = serde_json::to_string(&world).unwrap();
+            assert_eq!(serde_json::from_str::<World>(&json).unwrap(), world);
+        }
+    }
+
+    mod seeded_permute {
+        use super::sample_world;
+        use crate::*;
+
+        #[test]
+        fn test_powers() {
+            let rule = SeededPermute::new(21);
+            let start = sample_world(9, 14);
+            let bases: Vec<Vec<usize>> = start
+                .subsystems()
+                .iter()
+                .enumerate()
+                .map(|(i, s)| generate_random_permutation(s.cells.len(), 21 + i as u64))
+                .collect();
+            let mut powers: Vec<Vec<usize>> =
+                bases.iter().map(|p| (0..p.len()).collect()).collect();
+            let mut world = start.clone();
+            for _ in 0..6 {
+                rule.step(&mut world);
+                world.t += 1;
+                for (power, base) in powers.iter_mut().zip(&bases) {
+                    *power = compose_permutations(power, base);
+                }
+                let mut expected = start.clone();
+                Permute::from_permutations(powers.clone())
+                    .unwrap()
+                    .step(&mut expected);
+                expected.t = world.t;
+                assert_eq!(world, expected);
+            }
+        }
+
+        #[test]
+        fn test_inverse() {
+            let rule = SeededPermute::new(5);
+            let mut world = sample_world(9, 14);
+            rule.step(&mut world);
+            assert_ne!(world, sample_world(9, 14));
+            rule.inverse().step(&mut world);
+            assert_eq!(world, sample_world(9, 14));
+            assert_reversible(&rule, &sample_world(9, 14), 20);
+        }
+    }
+
+    mod thermalize {
+        use super::sample_world;
+        use crate::*;
+
+        #[test]
+        fn test_only_one_subsystem() {
+            let rule = Thermalize::new(BathId::Hot, 8);
+            let mut world = sample_world(-4, 20);
+            rule.step(&mut world);
+            let mut expected = sample_world(-4, 20);
+            expected
+                .subsystem_mut(BathId::Hot)
+                .permute(&generate_random_permutation(20, 4));
+            assert_eq!(world, expected);
+            assert_eq!(world.hot_bath_energy(), 10);
+        }
+
+        #[test]
+        fn test_inverse() {
+            let rule = Thermalize::new(BathId::Cold, 3);
+            let mut world = sample_world(-4, 20);
+            for _ in 0..5 {
+                rule.step(&mut world);
+                world.t += 1;
+            }
+            assert_ne!(world.cold_bath(), sample_world(-4, 20).cold_bath());
+            // The inverse undoes the step from `t - 1`, so it runs before the
+            // clock is wound back.
+            let inverse = rule.inverse();
+            for _ in 0..5 {
+                inverse.step(&mut world);
+                world.t -= 1;
+            }
+            assert_eq!(world, sample_world(-4, 20));
+            assert_reversible(&rule, &sample_world(-4, 20), 30);
+        }
+
+        #[test]
+        fn test_validate() {
+            let rule = Thermalize::new(BathId::Extra(0), 3);
+            assert_eq!(
+                rule.validate(&sample_world(-4, 20)),
+                Err(RuleError::MissingSubsystem {
+                    rule: "Thermalize",
+                    subsystem: BathId::Extra(0),
+                })
+            );
+        }
+    }
+
+    mod sim_config {
+        use crate::*;
+        #[test]
+        fn test_fill_patterns() {
+            let config = |fill_pattern| SimConfig {
+                battery_size: 2,
+                bath_size: 10,
+                hot_fill: 0.4,
+                cold_fill: 0.3,
+                fill_pattern,
+                ..SimConfig::default()
+            };
+            let block = World::from_config(&config(FillPattern::Block));
+            let interleaved = World::from_config(&config(FillPattern::Interleaved));
+            let random = World::from_config(&config(FillPattern::Random { seed: 4 }));
+            for world in [&block, &interleaved, &random] {
+                assert_eq!(world.hot_bath_energy(), 4);
+                assert_eq!(world.cold_bath_energy(), 3);
+                assert_eq!(world.battery_energy(), 0);
+            }
+            assert_eq!(format!("{block:#}"), "t=0\n..\n####......\n###.......");
+            assert_eq!(
+                format!("{interleaved:#}"),
+                "t=0\n..\n#..#.#..#.\n#...#..#.."
+            );
+            assert_ne!(random.hot_bath(), block.hot_bath());
+            assert_ne!(random.hot_bath(), interleaved.hot_bath());
+            assert_ne!(random.hot_bath(), random.cold_bath());
+            assert_eq!(
+                random,
+                World::from_config(&config(FillPattern::Random { seed: 4 }))
+            );
+        }
+
+        #[test]
+        fn test_interleaved_extremes() {
+            for (fill, expected) in [(0.0, "...."), (0.5, "#.#."), (1.0, "####")] {
+                let bath = FillPattern::Interleaved.fill(BathId::Hot, 4, fill);
+                let shown: String = bath.iter().map(|b| if b { '#' } else { '.' }).collect();
+                assert_eq!(shown, expected);
+            }
+            assert_eq!(FillPattern::Interleaved.fill(BathId::Hot, 0, 0.5).len(), 0);
+        }
+
+        #[test]
+        #[should_panic(expected = "fill must be in [0, 1], got 1.5")]
+        fn test_pattern_fill_out_of_range() {
+            FillPattern::Random { seed: 1 }.fill(BathId::Hot, 4, 1.5);
+        }
+
+        #[test]
+        fn test_from_config() {
+            let world = World::from_config(&SimConfig {
+                battery_size: 3,
+                bath_size: 4,
+                n_steps: 0,
+                ..SimConfig::default()
+            });
+            assert_eq!(
+                world,
+                World::classic(
+                    [false, false, false],
+                    [true, true, false, false],
+                    [false, false, false, false],
+                )
+            );
+        }
+
+        #[test]
+        fn test_new() {
+            let config = SimConfig::default();
+            assert_eq!(
+                World::new(config.battery_size, config.bath_size),
+                World::from_config(&config)
+            );
+            assert_eq!(World::new(0, 0), World::default());
+            assert_eq!(World::default(), World::builder().build());
+            assert_eq!(World::default().subsystems().len(), 3);
+        }
+
+        #[test]
+        fn test_from_config_odd() {
+            let world = World::from_config(&SimConfig {
+                battery_size: 2,
+                bath_size: 5,
+                n_steps: 0,
+                ..SimConfig::default()
+            });
+            assert_eq!(world.hot_bath().len(), 5);
+            assert_eq!(world.cold_bath().len(), 5);
+            assert_eq!(world.hot_bath_energy(), 3);
+            assert_eq!(
+                world.hot_bath(),
+                &BitBath::from([true, true, true, false, false])
+            );
+        }
+
+        #[test]
+        fn test_fills() {
+            for (fill, expected) in [(0.0, 0), (0.25, 5), (0.5, 10), (1.0, 20)] {
+                let world = World::from_config(&SimConfig {
+                    bath_size: 20,
+                    hot_fill: fill,
+                    cold_fill: fill,
+                    ..SimConfig::default()
+                });
+                for bath in [world.hot_bath(), world.cold_bath()] {
+                    assert_eq!(bath.count_ones(), expected);
+                    assert!((0..20).all(|i| bath.get(i) == (i < expected)));
+                }
+            }
+        }
+
+        #[test]
+        fn test_thermal() {
+            use rand::{rngs::StdRng, SeedableRng};
+            let config = SimConfig {
+                battery_size: 5,
+                bath_size: 10_000,
+                hot_fill: 0.3,
+                cold_fill: 0.0,
+                ..SimConfig::default()
+            };
+            let world = World::thermal(&config, &mut StdRng::seed_from_u64(4));
+            assert_eq!(world.battery(), &BitBath::new(5));
+            assert_eq!(world.cold_bath_energy(), 0);
+            // Binomial standard deviation is sqrt(10000 * 0.3 * 0.7) ~ 46.
+            let hot = world.hot_bath_energy() as f64 / 10_000.0;
+            assert!((hot - 0.3).abs() < 0.015, "hot fill {hot}");
+            // Random placement rather than a prefix.
+            assert_ne!(world.hot_bath(), World::from_config(&config).hot_bath());
+        }
+
+        #[test]
+        #[should_panic(expected = "cold_fill must be in [0, 1]")]
+        fn test_fill_out_of_range() {
+            World::from_config(&SimConfig {
+                cold_fill: 1.5,
+                ..SimConfig::default()
+            });
+        }
+    }
+
+    mod weird_conditional_permute {
+        use crate::*;
+        #[test]
+        fn test_inverse() {
+            let mut world = World::classic(
+                [
+                    false, true, false, true, false, true, false, true, false, true,
+                ],
+                [
+                    true, false, true, false, true, false, true, false, true, false,
+                ],
+                [false; 10],
+            );
+            let permute = WeirdPermute::new(0);
+            permute.step(&mut world);
+            world.t += 1;
+            permute.inverse().step(&mut world);
+            world.t -= 1;
+            assert_eq!(
+                world,
+                World::classic(
+                    [false, true, false, true, false, true, false, true, false, true,],
+                    [true, false, true, false, true, false, true, false, true, false,],
+                    [false; 10],
+                )
+            );
+        }
+
+        /// Reference implementation without the cache.
+        fn uncached(seed: u64, world: &mut World) {
+            let t = world.t;
+            for target in world.cells_mut() {
+                let perm = generate_random_permutation(target.len(), seed.wrapping_add_signed(t));
+                target.permute(&perm);
+            }
+        }
+
+        #[test]
+        fn test_cache_matches_uncached() {
+            let permute = WeirdPermute::new(6);
+            let start = World::builder()
+                .battery([true, false, false, true])
+                .hot_bath_half_full(20)
+                .cold_bath([false, true, true])
+                .build();
+            permute.precompute(&start, 3..6);
+            for _ in 0..2 {
+                let (mut a, mut b) = (start.clone(), start.clone());
+                for _ in 0..10 {
+                    permute.step(&mut a);
+                    a.t += 1;
+                    uncached(6, &mut b);
+                    b.t += 1;
+                    assert_eq!(a, b);
+                }
+            }
+            assert_eq!(permute.cache.borrow().len(), 3 * 10);
+            assert_reversible(&permute, &start, 10);
+        }
+
+        #[test]
+        fn test_lcg_shuffle() {
+            let permute = WeirdPermute::new(6).with_shuffle(Shuffle::Lcg);
+            assert_eq!(permute.shuffle(), Shuffle::Lcg);
+            let start = World::builder()
+                .t(2)
+                .battery([true, false, false, true])
+                .hot_bath_half_full(20)
+                .cold_bath([false, true, true])
+                .build();
+            let mut world = start.clone();
+            permute.step(&mut world);
+            let mut expected = start.clone();
+            for target in expected.cells_mut() {
+                target.permute(&fisher_yates_lcg(target.len(), 8));
+            }
+            assert_eq!(world, expected);
+
+            let mut std = start.clone();
+            WeirdPermute::new(6).step(&mut std);
+            assert_ne!(world, std);
+            assert_reversible(&permute, &start, 20);
+        }
+
+        #[test]
+        fn test_inverse_every_intermediate_state() {
+            for (seed, start_t, steps) in [
+                (0, 0, 20),
+                (0, -1, 2),
+                (7, -10, 20),
+                (7, -500, 40),
+                (u64::MAX, 3, 20),
+                (1, i64::MIN, 10),
+                (1, i64::MAX - 10, 10),
+            ] {
+                let permute = WeirdPermute::new(seed);
+                let inverse = permute.inverse();
+                let mut world = World::builder()
+                    .t(start_t)
+                    .battery([true, false, false, true, false])
+                    .hot_bath_half_full(12)
+                    .cold_bath([false, true, true])
+                    .build();
+                let mut history = vec![world.clone()];
+                for _ in 0..steps {
+                    permute.step(&mut world);
+                    world.t += 1;
+                    history.push(world.clone());
+                }
+                for expected in history.iter().rev().skip(1) {
+                    inverse.step(&mut world);
+                    world.t -= 1;
+                    assert_eq!(&world, expected, "seed={seed} start_t={start_t}");
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CondSwap;
+
+impl CondSwap {
+    pub fn apply(&self, view: WorldView) {
+        let WorldView { battery, hot, cold } = view;
+        if (hot[0], hot[1], cold[0], battery[0]) == (true, true, false, false)
+            || (hot[0], hot[1], cold[0], battery[0]) == (false, false, true, true)
+        {
+            hot.swap_with(0, cold, 0);
+            hot.swap_with(1, battery, 0);
+        }
+    }
+}
+
+impl Rule for CondSwap {
+    fn step(&self, world: &mut World) {
+        self.apply(world.view());
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(Self)
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(self.clone())
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        self.footprint().check("CondSwap", world)
+    }
+
+    fn footprint(&self) -> Footprint {
+        Footprint::from_cells([
+            (BathId::Hot, 0),
+            (BathId::Hot, 1),
+            (BathId::Cold, 0),
+            (BathId::Battery, 0),
+        ])
+    }
+}
+
+/// A Fredkin gate: swaps cells `a` and `b` if cell `control` is occupied.
+///
+/// `control` must be distinct from `a` and `b`, so the swap can't change
+/// whether it fires; that makes the gate its own inverse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ControlledSwap {
+    pub control: (BathId, usize),
+    pub a: (BathId, usize),
+    pub b: (BathId, usize),
+}
+
+impl ControlledSwap {
+    pub fn new(control: (BathId, usize), a: (BathId, usize), b: (BathId, usize)) -> ControlledSwap {
+        assert!(
+            control != a && control != b,
+            "swapping the control cell would make ControlledSwap irreversible"
+        );
+        ControlledSwap { control, a, b }
+    }
+}
+
+impl Rule for ControlledSwap {
+    fn step(&self, world: &mut World) {
+        if world.get(self.control) {
+            world.swap_cells(self.a, self.b);
+        }
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(*self)
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(*self)
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        self.footprint().check("ControlledSwap", world)
+    }
+
+    fn footprint(&self) -> Footprint {
+        Footprint::from_cells([self.control, self.a, self.b])
+    }
+}
+
+/// A controlled-NOT: flips cell `target` if cell `control` is occupied.
+///
+/// `control` and `target` must be distinct cells, which makes the gate its
+/// own inverse. Unlike the swap rules it doesn't conserve energy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CNot {
+    pub control: (BathId, usize),
+    pub target: (BathId, usize),
+}
+
+impl CNot {
+    pub fn new(control: (BathId, usize), target: (BathId, usize)) -> CNot {
+        assert_ne!(
+            control, target,
+            "a CNot targeting its own control would be irreversible"
+        );
+        CNot { control, target }
+    }
+}
+
+impl Rule for CNot {
+    fn step(&self, world: &mut World) {
+        if world.get(self.control) {
+            let (id, i) = self.target;
+            let bath = world.subsystem_mut(id);
+            bath.set(i, !bath.get(i));
+        }
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(*self)
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(*self)
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        self.footprint().check("CNot", world)
+    }
+
+    fn footprint(&self) -> Footprint {
+        Footprint::from_cells([self.control, self.target])
+    }
+}
+
+/// Swaps cells `a` and `b` with probability `probability` each step, for a
+/// weak coupling between them. The coin is a hash of `seed + t` rather than
+/// an RNG draw, so it's the same every time a given step is replayed.
+///
+/// Like `WeirdPermute`, it's only reversible in the seeded sense: the
+/// inverse re-flips the coin for `t - 1`, so it needs the clock to be where
+/// the forward step left it, and then undoes the swap if there was one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StochasticSwap {
+    pub a: (BathId, usize),
+    pub b: (BathId, usize),
+    pub probability: f64,
+    pub seed: u64,
+    pub inverted: bool,
+}
+
+impl StochasticSwap {
+    pub fn new(
+        a: (BathId, usize),
+        b: (BathId, usize),
+        probability: f64,
+        seed: u64,
+    ) -> StochasticSwap {
+        assert!(
+            (0.0..=1.0).contains(&probability),
+            "probability must be in [0, 1], got {probability}"
+        );
+        StochasticSwap {
+            a,
+            b,
+            probability,
+            seed,
+            inverted: false,
+        }
+    }
+
+    /// Whether the step at `t` swaps.
+    pub fn fires(&self, t: i64) -> bool {
+        let mut state = self.seed.wrapping_add_signed(t);
+        // The top 53 bits as a uniform draw from [0, 1).
+        let draw = (splitmix64(&mut state) >> 11) as f64 / (1u64 << 53) as f64;
+        draw < self.probability
+    }
+}
+
+impl Rule for StochasticSwap {
+    fn step(&self, world: &mut World) {
+        let t = if self.inverted {
+            world.t.wrapping_sub(1)
+        } else {
+            world.t
+        };
+        if self.fires(t) {
+            world.swap_cells(self.a, self.b);
+        }
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(StochasticSwap {
+            inverted: !self.inverted,
+            ..*self
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(*self)
+    }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.seed)
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        self.footprint().check("StochasticSwap", world)
+    }
+
+    fn footprint(&self) -> Footprint {
+        Footprint::from_cells([self.a, self.b])
+    }
+}
+
+/// Swaps the whole hot and cold baths if cell `control` is occupied.
+///
+/// `control` must be outside both baths, so the swap can't change whether
+/// it fires; that makes the rule its own inverse. The baths must be the same
+/// length.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConditionalBathSwap {
+    pub control: (BathId, usize),
+}
+
+impl ConditionalBathSwap {
+    pub fn new(control: (BathId, usize)) -> ConditionalBathSwap {
+        assert!(
+            control.0 != BathId::Hot && control.0 != BathId::Cold,
+            "a control cell in a swapped bath would make ConditionalBathSwap irreversible"
+        );
+        ConditionalBathSwap { control }
+    }
+}
+
+impl Rule for ConditionalBathSwap {
+    fn step(&self, world: &mut World) {
+        if world.get(self.control) {
+            let (hot, cold) = world.subsystem_pair_mut(BathId::Hot, BathId::Cold);
+            std::mem::swap(hot, cold);
+        }
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(*self)
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(*self)
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        self.footprint().check("ConditionalBathSwap", world)?;
+        let expected = world.hot_bath().len();
+        if world.cold_bath().len() != expected {
+            return Err(RuleError::WrongLength {
+                rule: "ConditionalBathSwap",
+                subsystem: BathId::Cold,
+                expected,
+                len: world.cold_bath().len(),
+            });
+        }
+        Ok(())
+    }
+
+    fn footprint(&self) -> Footprint {
+        Footprint::from_cells([self.control])
+            .union(&Footprint::from_subsystems([BathId::Hot, BathId::Cold]))
+    }
+}
+
+impl Rule for Vec<Box<dyn Rule>> {
+    fn step(&self, world: &mut World) {
+        for rule in self {
+            rule.step(world);
+        }
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        let mut inverse = Vec::new();
+        for rule in self.iter().rev() {
+            inverse.push(rule.inverse());
+        }
+        Box::new(inverse)
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(self.clone())
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        self.iter().try_for_each(|rule| rule.validate(world))
+    }
+
+    fn seed(&self) -> Option<u64> {
+        self.iter().find_map(|rule| rule.seed())
+    }
+
+    fn footprint(&self) -> Footprint {
+        self.iter()
+            .fold(Footprint::new(), |acc, rule| acc.union(&rule.footprint()))
+    }
+}
+
+/// `first` followed by `second`; see `Rule::then`. Like a two-element
+/// `Vec<Box<dyn Rule>>`, its inverse undoes `second` before `first`.
+#[derive(Clone)]
+pub struct Then {
+    pub first: Box<dyn Rule>,
+    pub second: Box<dyn Rule>,
+}
+
+impl Rule for Then {
+    fn step(&self, world: &mut World) {
+        self.first.step(world);
+        self.second.step(world);
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(Then {
+            first: self.second.inverse(),
+            second: self.first.inverse(),
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(self.clone())
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        self.first.validate(world)?;
+        self.second.validate(world)
+    }
+
+    fn seed(&self) -> Option<u64> {
+        self.first.seed().or_else(|| self.second.seed())
+    }
+
+    fn footprint(&self) -> Footprint {
+        self.first.footprint().union(&self.second.footprint())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoOp;
+
+impl Rule for NoOp {
+    fn step(&self, _world: &mut World) {}
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(NoOp)
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(*self)
+    }
+}
+
+/// A Margolus-style local rule: `subsystem` is cut into consecutive blocks of
+/// `block_size` cells and each block's contents are replaced according to
+/// `table`, a permutation of the `2^block_size` block states (cell `j` of a
+/// block is bit `j` of its state).
+///
+/// Blocks start at cell 0 on even `t` and at cell 1 on odd `t`, so that
+/// information can cross block boundaries. Cells that don't fit in a whole
+/// block at the end of the bath are left alone.
+#[derive(Clone, Debug)]
+pub struct BlockRule {
+    subsystem: BathId,
+    block_size: usize,
+    table: Vec<usize>,
+    inverted: bool,
+}
+
+impl BlockRule {
+    pub fn new(
+        subsystem: BathId,
+        block_size: usize,
+        table: Vec<usize>,
+    ) -> Result<BlockRule, PermuteError> {
+        assert!(
+            (1..=16).contains(&block_size),
+            "block size must be between 1 and 16"
+        );
+        if table.len() != 1 << block_size {
+            return Err(PermuteError::WrongLength {
+                subsystem,
+                expected: 1 << block_size,
+                len: table.len(),
+            });
+        }
+        check_permutation(subsystem, &table)?;
+        Ok(BlockRule {
+            subsystem,
+            block_size,
+            table,
+            inverted: false,
+        })
+    }
+}
+
+impl Rule for BlockRule {
+    fn step(&self, world: &mut World) {
+        // Same trick as WeirdPermute: the inverse runs one tick later than the
+        // step it undoes, and must use that step's block offset.
+        let t = if self.inverted {
+            world.t.wrapping_sub(1)
+        } else {
+            world.t
+        };
+        let k = self.block_size;
+        let bath = world.subsystem_mut(self.subsystem);
+        let mut start = t.rem_euclid(2) as usize;
+        while start + k <= bath.len() {
+            let state = (0..k).fold(0, |acc, j| acc | (bath.get(start + j) as usize) << j);
+            let new_state = self.table[state];
+            for j in 0..k {
+                bath.set(start + j, new_state >> j & 1 == 1);
+            }
+            start += k;
+        }
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(BlockRule {
+            subsystem: self.subsystem,
+            block_size: self.block_size,
+            table: invert_permutation(&self.table),
+            inverted: !self.inverted,
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(self.clone())
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        self.footprint().check("BlockRule", world)
+    }
+
+    fn footprint(&self) -> Footprint {
+        Footprint::from_subsystems([self.subsystem])
+    }
+}
+
+/// Rotates one subsystem so each cell's contents move `offset` cells to the
+/// right (left if negative), wrapping around the end of the bath.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CyclicShift {
+    pub subsystem: BathId,
+    pub offset: isize,
+}
+
+impl Rule for CyclicShift {
+    fn step(&self, world: &mut World) {
+        let bath = world.subsystem_mut(self.subsystem);
+        if bath.is_empty() {
+            return;
+        }
+        bath.rotate_right(self.offset.rem_euclid(bath.len() as isize) as usize);
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        let shift = |offset| CyclicShift {
+            subsystem: self.subsystem,
+            offset,
+        };
+        match self.offset.checked_neg() {
+            Some(offset) => Box::new(shift(offset)),
+            // -isize::MIN doesn't fit, so get there in two shifts.
+            None => shift(isize::MAX).then(shift(1)),
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(*self)
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        self.footprint().check("CyclicShift", world)
+    }
+
+    fn footprint(&self) -> Footprint {
+        Footprint::from_subsystems([self.subsystem])
+    }
+}
+
+/// Reverses the order of one subsystem's cells. Doing it twice restores the
+/// original, so it's its own inverse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReflectBath {
+    pub subsystem: BathId,
+}
+
+impl Rule for ReflectBath {
+    fn step(&self, world: &mut World) {
+        world.subsystem_mut(self.subsystem).reverse();
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(*self)
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(*self)
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        self.footprint().check("ReflectBath", world)
+    }
+
+    fn footprint(&self) -> Footprint {
+        Footprint::from_subsystems([self.subsystem])
+    }
+}
+
+/// One round of an odd-even transposition sort on a subsystem: every
+/// occupied cell whose right-hand neighbour is empty moves into it, looking
+/// at the pairs `(i, i + 1)` with even `i` on even `t` and odd `i` on odd
+/// `t`. Applied repeatedly, it sorts the bath's energy toward its last cell.
+///
+/// Sorting throws information away, so each swap is recorded in `ancilla`,
+/// a separate subsystem used as rows of `id`'s length, one row per step
+/// (cycling when it runs out). The pair at `i` swaps from (occupied, empty)
+/// to (empty, occupied) only if its cell in this step's row is clear, and
+/// sets it; a pair that's (empty, occupied) with its cell set goes back and
+/// clears it. That's a bijection, and it's its own inverse run at `t - 1`.
+/// With a clear ancilla of at least as many rows as steps, it sorts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OddEvenStep {
+    pub id: BathId,
+    pub ancilla: BathId,
+    pub inverted: bool,
+}
+
+impl OddEvenStep {
+    pub fn new(id: BathId, ancilla: BathId) -> OddEvenStep {
+        OddEvenStep {
+            id,
+            ancilla,
+            inverted: false,
+        }
+    }
+}
+
+impl Rule for OddEvenStep {
+    fn step(&self, world: &mut World) {
+        let t = if self.inverted {
+            world.t.wrapping_sub(1)
+        } else {
+            world.t
+        };
+        let len = world.subsystem(self.id).len();
+        let rows = world.subsystem(self.ancilla).len() / len.max(1);
+        if len < 2 || rows == 0 {
+            return;
+        }
+        let row = t.rem_euclid(rows as i64) as usize * len;
+        for i in (t.rem_euclid(2) as usize..len - 1).step_by(2) {
+            let (left, right) = ((self.id, i), (self.id, i + 1));
+            let recorded = world.get((self.ancilla, row + i));
+            if world.get(left) != recorded && world.get(right) == recorded {
+                world.swap_cells(left, right);
+                world.subsystem_mut(self.ancilla).set(row + i, !recorded);
+            }
+        }
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(OddEvenStep {
+            inverted: !self.inverted,
+            ..*self
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(*self)
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        self.footprint().check("OddEvenStep", world)?;
+        let needed = world.subsystem(self.id).len();
+        let len = world.subsystem(self.ancilla).len();
+        if self.ancilla == self.id || len < needed {
+            return Err(RuleError::Ancilla {
+                rule: "OddEvenStep",
+                ancilla: self.ancilla,
+                needed,
+                len,
+            });
+        }
+        Ok(())
+    }
+
+    fn footprint(&self) -> Footprint {
+        Footprint::from_subsystems([self.id, self.ancilla])
+    }
+}
+
+/// Runs `inner` only when `predicate` holds.
+///
+/// This is only reversible if `inner` can't change the predicate's answer:
+/// the inverse checks the same predicate *after* the forward step, so it
+/// must depend only on quantities `inner` conserves (e.g. a bath's energy
+/// under a permutation) or that it doesn't touch.
+#[derive(Clone)]
+pub struct Conditional {
+    pub predicate: Rc<dyn Fn(&World) -> bool>,
+    pub inner: Box<dyn Rule>,
+}
+
+impl Rule for Conditional {
+    fn step(&self, world: &mut World) {
+        if (self.predicate)(world) {
+            self.inner.step(world);
+        }
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(Conditional {
+            predicate: self.predicate.clone(),
+            inner: self.inner.inverse(),
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(self.clone())
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        self.inner.validate(world)
+    }
+
+    fn seed(&self) -> Option<u64> {
+        self.inner.seed()
+    }
+
+    /// Only `inner`'s cells: whatever the predicate reads is opaque.
+    fn footprint(&self) -> Footprint {
+        self.inner.footprint()
+    }
+}
+
+/// Runs the rule of the first segment whose range contains `t`, or nothing
+/// if none does.
+///
+/// The inverse inverts every segment's rule but keeps the segments in the
+/// same order, so overlapping ranges resolve to the same segment in both
+/// directions.
+#[derive(Clone)]
+pub struct Scheduled {
+    pub segments: Vec<(Range<i64>, Box<dyn Rule>)>,
+    pub inverted: bool,
+}
+
+impl Scheduled {
+    pub fn new(segments: Vec<(Range<i64>, Box<dyn Rule>)>) -> Scheduled {
+        Scheduled {
+            segments,
+            inverted: false,
+        }
+    }
+}
+
+impl Rule for Scheduled {
+    fn step(&self, world: &mut World) {
+        // Like WeirdPermute, the inverse must pick the segment that was
+        // active for the step it undoes, one tick earlier.
+        let t = if self.inverted {
+            world.t.wrapping_sub(1)
+        } else {
+            world.t
+        };
+        if let Some((_, rule)) = self.segments.iter().find(|(range, _)| range.contains(&t)) {
+            rule.step(world);
+        }
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(Scheduled {
+            segments: self
+                .segments
+                .iter()
+                .map(|(range, rule)| (range.clone(), rule.inverse()))
+                .collect(),
+            inverted: !self.inverted,
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(self.clone())
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        self.segments
+            .iter()
+            .try_for_each(|(_, rule)| rule.validate(world))
+    }
+
+    fn seed(&self) -> Option<u64> {
+        self.segments.iter().find_map(|(_, rule)| rule.seed())
+    }
+
+    fn footprint(&self) -> Footprint {
+        self.segments
+            .iter()
+            .fold(Footprint::new(), |acc, (_, rule)| {
+                acc.union(&rule.footprint())
+            })
+    }
+}
+
+/// Applies `inner` `times` times within a single step. All the applications
+/// see the same `t`, so the inverse is just the inner inverse repeated.
+#[derive(Clone)]
+pub struct Repeat {
+    pub inner: Box<dyn Rule>,
+    pub times: usize,
+}
+
+impl Rule for Repeat {
+    fn step(&self, world: &mut World) {
+        for _ in 0..self.times {
+            self.inner.step(world);
+        }
+    }
+
+    fn inverse(&self) -> Box<dyn Rule> {
+        Box::new(Repeat {
+            inner: self.inner.inverse(),
+            times: self.times,
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn Rule> {
+        Box::new(self.clone())
+    }
+
+    fn validate(&self, world: &World) -> Result<(), RuleError> {
+        self.inner.validate(world)
+    }
+
+    fn seed(&self) -> Option<u64> {
+        self.inner.seed()
+    }
+
+    fn footprint(&self) -> Footprint {
+        self.inner.footprint()
+    }
+}
+
+/// Builds a rule set fluently, e.g.
+/// `RuleSet::new().push(CondSwap).repeat(WeirdPermute::new(1), 3).build()`.
+#[derive(Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl RuleSet {
+    pub fn new() -> RuleSet {
+        RuleSet::default()
+    }
+
+    pub fn push(mut self, rule: impl Rule + 'static) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Appends `rule` applied `times` times within each step, as a `Repeat`.
+    pub fn repeat(self, rule: impl Rule + 'static, times: usize) -> Self {
+        self.push(Repeat {
+            inner: Box::new(rule),
+            times,
+        })
+    }
+
+    /// The inverse of the set built so far.
+    pub fn inverse(&self) -> Box<dyn Rule> {
+        self.rules.inverse()
+    }
+
+    pub fn build(self) -> Vec<Box<dyn Rule>> {
+        self.rules
+    }
+}
+
+/// The rules `main` runs: `CondSwap` followed by a `WeirdPermute` mixing step.
+pub fn default_rules(seed: u64) -> Vec<Box<dyn Rule>> {
+    vec![Box::new(CondSwap), Box::new(WeirdPermute::new(seed))]
+}
+
+/// Runs `rule` forward `steps` times from `world`, then its inverse backward
+/// the same number of times, and panics unless we end up where we started.
+pub fn assert_reversible(rule: &dyn Rule, world: &World, steps: u64) {
+    let inverse = rule.inverse();
+    let mut w = world.clone();
+    for _ in 0..steps {
+        rule.step(&mut w);
+        w.t += 1;
+    }
+    for _ in 0..steps {
+        inverse.step(&mut w);
+        w.t -= 1;
+    }
+    assert_eq!(&w, world, "rule failed to reverse after {steps} steps");
+}
+
+/// The output for a given `(n, seed)` must never change: `WeirdPermute`
+/// regenerates the same permutation when running backward, so any drift
+/// between versions would break inverses of recorded runs.
+pub fn generate_random_permutation(n: usize, seed: u64) -> Vec<usize> {
+    generate_random_permutation_with(n, &mut rand::rngs::StdRng::seed_from_u64(seed))
+}
+
+/// A Fisher-Yates shuffle of `0..n` driven by splitmix64 instead of `rand`,
+/// so it needs no RNG setup and is much cheaper per call. The output is
+/// deterministic in `(n, seed)` and must never change, for the same reason
+/// as `generate_random_permutation`'s, but it's a lower-quality shuffle.
+pub fn fisher_yates_lcg(n: usize, seed: u64) -> Vec<usize> {
+    let mut state = seed;
+    let mut next = || splitmix64(&mut state);
+    let mut permutation = (0..n).collect::<Vec<usize>>();
+    for i in (1..n).rev() {
+        // Scale into `0..=i` by the high half of a widening multiply.
+        let j = ((next() as u128 * (i as u128 + 1)) >> 64) as usize;
+        permutation.swap(i, j);
+    }
+    permutation
+}
+
+/// Advances `state` and returns the next splitmix64 output.
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A `Permute` shuffling each of `world`'s subsystems with a permutation
+/// drawn from `rng`. It conserves every subsystem's energy and always fits
+/// `world`.
+pub fn random_permutation_rule<R: RngCore>(world: &World, rng: &mut R) -> Permute {
+    Permute {
+        permutations: world
+            .subsystems()
+            .iter()
+            .map(|s| generate_random_permutation_with(s.cells.len(), rng))
+            .collect(),
+    }
+}
+
+pub fn generate_random_permutation_with<R: RngCore>(n: usize, rng: &mut R) -> Vec<usize> {
+    let mut permutation = (0..n).collect::<Vec<usize>>();
+    permutation.shuffle(rng);
+    permutation
+}
+
+pub fn permute<T: Clone, S: AsMut<[T]>>(permutation: &[usize], xs: &mut S) {
+    let buf = xs.as_mut();
+    let orig = buf.to_vec();
+    for i in 0..buf.len() {
+        buf[i] = orig[permutation[i]].clone();
+    }
+}
+
+/// Same result as `permute`, but without copying `xs`: it follows each cycle
+/// of `permutation`, swapping elements into place, and only allocates a
+/// bitset to remember which cells are done.
+pub fn permute_in_place<T, S: AsMut<[T]>>(permutation: &[usize], xs: &mut S) {
+    let buf = xs.as_mut();
+    assert_eq!(permutation.len(), buf.len(), "permutation length mismatch");
+    let mut done = BitBath::new(buf.len());
+    for start in 0..buf.len() {
+        if done.get(start) {
+            continue;
+        }
+        // `buf[start]` travels around the cycle, leaving each cell it passes
+        // holding the element the permutation asks for.
+        let mut i = start;
+        loop {
+            done.set(i, true);
+            let src = permutation[i];
+            if src == start {
+                break;
+            }
+            buf.swap(i, src);
+            i = src;
+        }
+    }
+}
+
+pub fn invert_permutation(permutation: &[usize]) -> Vec<usize> {
+    let mut inverse = vec![0; permutation.len()];
+    for i in 0..permutation.len() {
+        inverse[permutation[i]] = i;
+    }
+    inverse
+}
+
+/// The single permutation equivalent to `permute(first, ..)` followed by
+/// `permute(second, ..)`.
+pub fn compose_permutations(first: &[usize], second: &[usize]) -> Vec<usize> {
+    assert_eq!(
+        first.len(),
+        second.len(),
+        "can't compose permutations of different lengths"
+    );
+    second.iter().map(|&i| first[i]).collect()
+}
+
+#[cfg(test)]
+mod test_generate_random_permutation {
+    use crate::*;
+    use rand::rngs::{mock::StepRng, StdRng};
+
+    #[test]
+    fn test_with_step_rng() {
+        let a = generate_random_permutation_with(10, &mut StepRng::new(3, 7));
+        let b = generate_random_permutation_with(10, &mut StepRng::new(3, 7));
+        assert_eq!(a, b);
+        let mut sorted = a.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_seeded_wrapper() {
+        assert_eq!(
+            generate_random_permutation(50, 9),
+            generate_random_permutation_with(50, &mut StdRng::seed_from_u64(9))
+        );
+    }
+
+    #[test]
+    fn test_fisher_yates_lcg() {
+        for (n, seed) in [(0, 1), (1, 1), (10, 42), (1000, 7)] {
+            let mut sorted = fisher_yates_lcg(n, seed);
+            sorted.sort();
+            assert_eq!(sorted, (0..n).collect::<Vec<_>>());
+        }
+        assert_ne!(fisher_yates_lcg(100, 1), fisher_yates_lcg(100, 2));
+        // Pinned, since `WeirdPermute` inverses regenerate it.
+        assert_eq!(fisher_yates_lcg(10, 42), vec![8, 3, 6, 5, 4, 0, 9, 2, 1, 7]);
+    }
+
+    #[test]
+    fn test_random_permutation_rule() {
+        let mut world = World::builder()
+            .battery([true, false, true])
+            .hot_bath_half_full(30)
+            .cold_bath([false, true, false, false])
+            .build();
+        world.add_subsystem("warm", [true, true, false, false, false]);
+        let mut rng = StdRng::seed_from_u64(5);
+        for _ in 0..20 {
+            let rule = random_permutation_rule(&world, &mut rng);
+            assert_eq!(rule.validate(&world), Ok(()));
+            let before: Vec<usize> = world.ids().map(|id| world.energy(id)).collect();
+            rule.step(&mut world);
+            let after: Vec<usize> = world.ids().map(|id| world.energy(id)).collect();
+            assert_eq!(before, after);
+        }
+    }
+
+    #[test]
+    fn test_shared_rng_advances() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let a = generate_random_permutation_with(50, &mut rng);
+        let b = generate_random_permutation_with(50, &mut rng);
+        assert_ne!(a, b);
+    }
+}
+
+#[cfg(test)]
+mod test_invert_permutation {
+    use crate::*;
+    #[test]
+    fn test_invert_permutation() {
+        let mut xs = vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let permutation = generate_random_permutation(xs.len(), 0);
+        permute(&permutation, &mut xs);
+        permute(&invert_permutation(&permutation), &mut xs);
+        assert_eq!(xs, vec![0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_in_place_fixed_points() {
+        for permutation in [
+            vec![0, 1, 2, 3, 4],
+            vec![0, 3, 2, 4, 1],
+            vec![4, 1, 2, 3, 0],
+        ] {
+            let mut expected = vec!["a", "b", "c", "d", "e"];
+            permute(&permutation, &mut expected);
+            let mut xs = vec!["a", "b", "c", "d", "e"];
+            permute_in_place(&permutation, &mut xs);
+            assert_eq!(xs, expected);
+        }
+        permute_in_place(&[], &mut Vec::<u8>::new());
+    }
+}
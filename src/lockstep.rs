@@ -0,0 +1,72 @@
+use crate::{default_rules, PowerOfTwoSampler, SimConfig, Simulation};
+
+/// Runs two copies of `Simulation::seeded(config, seed)` side by side for
+/// `steps` steps, one forward and one with `step_back`, and checks that they
+/// stay consistent: after `k` steps each, stepping the backward copy forward
+/// `2 * k` steps must land exactly on the forward one. That's checked at
+/// every power-of-two `k` and after the last step, so the replays cost at
+/// most about six times as many steps as `steps` on top of the run itself.
+///
+/// Returns false at the first inconsistency, which means the rules' inverse
+/// doesn't really undo them from some state.
+pub fn run_lockstep(config: &SimConfig, seed: u64, steps: u64) -> bool {
+    let mut forward = Simulation::seeded(config, seed);
+    let mut backward = Simulation::seeded(config, seed);
+    let mut sampler = PowerOfTwoSampler::new();
+    for k in 1..=steps {
+        forward.step();
+        backward.step_back();
+        if sampler.sample(k) || k == steps {
+            let mut replay = Simulation::new(backward.world().clone(), default_rules(seed));
+            replay.step_n(2 * k);
+            if replay.world() != forward.world() {
+                return false;
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Rule, World};
+
+    fn config() -> SimConfig {
+        SimConfig {
+            battery_size: 10,
+            bath_size: 40,
+            ..SimConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_default_rules_consistent() {
+        for seed in [0, 1, 99] {
+            assert!(run_lockstep(&config(), seed, 300));
+        }
+        assert!(run_lockstep(&config(), 5, 0));
+    }
+
+    #[test]
+    fn test_halves_meet() {
+        // What `run_lockstep` checks, spelled out: the backward copy after
+        // `k` steps is the state `2 * k` steps before the forward copy.
+        let (config, seed) = (config(), 3);
+        let mut backward = Simulation::seeded(&config, seed);
+        for _ in 0..4 {
+            backward.step_back();
+        }
+        let mut world = backward.world().clone();
+        assert_eq!(world.t, -4);
+        let rules = default_rules(seed);
+        for _ in 0..8 {
+            rules.step(&mut world);
+            world.t += 1;
+        }
+        let mut forward = Simulation::seeded(&config, seed);
+        forward.step_n(4);
+        assert_eq!(&world, forward.world());
+        assert_ne!(world, World::from_config(&config));
+    }
+}
@@ -0,0 +1,222 @@
+use crate::{BitBath, World};
+
+/// Entropy in bits of a Bernoulli variable with `p` equal to the fraction of
+/// occupied cells. Empty, full, and zero-length baths all have zero entropy.
+pub fn occupation_entropy(bath: &BitBath) -> f64 {
+    if bath.is_empty() {
+        return 0.0;
+    }
+    let p = bath.count_ones() as f64 / bath.len() as f64;
+    if p == 0.0 || p == 1.0 {
+        return 0.0;
+    }
+    -p * p.log2() - (1.0 - p) * (1.0 - p).log2()
+}
+
+/// The dimensionless temperature `T` at which a two-level system with the
+/// bath's occupied fraction `p` is in equilibrium: `p / (1 - p) = exp(-1/T)`.
+///
+/// A half-full bath is infinitely hot, and more than half full means a
+/// negative temperature. Empty, full, and zero-length baths have none
+/// (`T` would be zero or `-0`), so they give `None`.
+pub fn effective_temperature(bath: &BitBath) -> Option<f64> {
+    let (ones, len) = (bath.count_ones(), bath.len());
+    if ones == 0 || ones == len {
+        return None;
+    }
+    let zeros = len - ones;
+    Some(1.0 / (zeros as f64 / ones as f64).ln())
+}
+
+/// The autocorrelation of `series` at each lag `0..=max_lag`: the
+/// covariance between `x[i]` and `x[i + lag]` over the overlapping part,
+/// divided by the series' variance, both about the whole series' mean. Lag 0
+/// is always 1, and lags past the end of the series are 0.
+///
+/// A constant (or empty) series has no variance to normalize by; it counts
+/// as perfectly correlated with itself, so every lag is 1.
+pub fn autocorrelation(series: &[f64], max_lag: usize) -> Vec<f64> {
+    let n = series.len() as f64;
+    let mean = series.iter().sum::<f64>() / n;
+    let deviations: Vec<f64> = series.iter().map(|x| x - mean).collect();
+    let variance: f64 = deviations.iter().map(|d| d * d).sum();
+    if variance == 0.0 {
+        return vec![1.0; max_lag + 1];
+    }
+    (0..=max_lag)
+        .map(|lag| {
+            let covariance: f64 = deviations
+                .iter()
+                .zip(deviations.iter().skip(lag))
+                .map(|(a, b)| a * b)
+                .sum();
+            covariance / variance
+        })
+        .collect()
+}
+
+/// The trailing moving average of `series`: element `i` is the mean of the
+/// last `window` values up to and including `series[i]`. Near the start,
+/// where fewer than `window` values exist, it's the mean of all of them, so
+/// the output is as long as the input and a `window` longer than the series
+/// gives its running mean.
+///
+/// Panics if `window` is zero.
+pub fn moving_average(series: &[f64], window: usize) -> Vec<f64> {
+    assert!(window > 0, "moving_average window must be positive");
+    let mut sum = 0.0;
+    series
+        .iter()
+        .enumerate()
+        .map(|(i, x)| {
+            sum += x;
+            if i >= window {
+                sum -= series[i - window];
+            }
+            sum / (i + 1).min(window) as f64
+        })
+        .collect()
+}
+
+/// The best efficiency any engine can reach running between baths at these
+/// temperatures: `1 - t_cold / t_hot`.
+pub fn carnot_efficiency(t_hot: f64, t_cold: f64) -> f64 {
+    1.0 - t_cold / t_hot
+}
+
+impl World {
+    pub fn battery_entropy(&self) -> f64 {
+        occupation_entropy(self.battery())
+    }
+
+    pub fn hot_bath_entropy(&self) -> f64 {
+        occupation_entropy(self.hot_bath())
+    }
+
+    pub fn cold_bath_entropy(&self) -> f64 {
+        occupation_entropy(self.cold_bath())
+    }
+
+    /// Each subsystem's `occupation_entropy` times its length, summed: the
+    /// entropy in bits of the whole world if every cell were independent.
+    pub fn total_entropy(&self) -> f64 {
+        self.subsystems()
+            .iter()
+            .map(|s| s.cells.len() as f64 * occupation_entropy(&s.cells))
+            .sum()
+    }
+
+    pub fn battery_temperature(&self) -> Option<f64> {
+        effective_temperature(self.battery())
+    }
+
+    pub fn hot_bath_temperature(&self) -> Option<f64> {
+        effective_temperature(self.hot_bath())
+    }
+
+    pub fn cold_bath_temperature(&self) -> Option<f64> {
+        effective_temperature(self.cold_bath())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_half_full() {
+        assert_eq!(occupation_entropy(&BitBath::from([true, false])), 1.0);
+        let world = World::builder().hot_bath_half_full(200).build();
+        assert_eq!(world.hot_bath_entropy(), 1.0);
+    }
+
+    #[test]
+    fn test_total_entropy() {
+        let world = World::classic([true, false], [true, false, false, false], [true; 3]);
+        assert!((world.total_entropy() - (2.0 + 4.0 * 0.8112781244591328)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_full_and_empty() {
+        assert_eq!(occupation_entropy(&BitBath::from([true; 7])), 0.0);
+        assert_eq!(occupation_entropy(&BitBath::new(7)), 0.0);
+        assert_eq!(occupation_entropy(&BitBath::new(0)), 0.0);
+    }
+
+    #[test]
+    fn test_temperature() {
+        let quarter = BitBath::from([true, false, false, false]);
+        let t = effective_temperature(&quarter).unwrap();
+        assert!((t - 1.0 / 3f64.ln()).abs() < 1e-12);
+        assert!(t > 0.0);
+
+        let world = World::builder().hot_bath_half_full(10).build();
+        assert_eq!(world.hot_bath_temperature(), Some(f64::INFINITY));
+
+        // Population inversion: more occupied than empty cells.
+        let three_quarters = BitBath::from([true, true, false, true]);
+        assert_eq!(effective_temperature(&three_quarters), Some(-t));
+    }
+
+    #[test]
+    fn test_temperature_undefined() {
+        assert_eq!(effective_temperature(&BitBath::from([true; 5])), None);
+        assert_eq!(effective_temperature(&BitBath::new(5)), None);
+        assert_eq!(effective_temperature(&BitBath::new(0)), None);
+        assert_eq!(World::builder().build().cold_bath_temperature(), None);
+    }
+
+    #[test]
+    fn test_autocorrelation_periodic() {
+        let series: Vec<f64> = [0.0, 1.0, 0.0, -1.0].repeat(10);
+        let r = autocorrelation(&series, 8);
+        assert_eq!(r[0], 1.0);
+        assert!(r[2] < 0.0);
+        let peak = (1..=6).max_by(|&a, &b| r[a].total_cmp(&r[b])).unwrap();
+        assert_eq!(peak, 4);
+        assert!((r[4] - 36.0 / 40.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_autocorrelation_degenerate() {
+        assert_eq!(autocorrelation(&[3.0; 5], 2), vec![1.0; 3]);
+        assert_eq!(autocorrelation(&[], 1), vec![1.0; 2]);
+        assert_eq!(autocorrelation(&[1.0, 2.0], 3)[2..], [0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_moving_average_ramp() {
+        let ramp: Vec<f64> = (0..6).map(f64::from).collect();
+        assert_eq!(moving_average(&ramp, 3), vec![0.0, 0.5, 1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(moving_average(&ramp, 1), ramp);
+        assert_eq!(
+            moving_average(&ramp, 100),
+            vec![0.0, 0.5, 1.0, 1.5, 2.0, 2.5]
+        );
+    }
+
+    #[test]
+    fn test_moving_average_constant() {
+        assert_eq!(moving_average(&[2.5; 7], 4), vec![2.5; 7]);
+        assert_eq!(moving_average(&[], 4), Vec::<f64>::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "window must be positive")]
+    fn test_moving_average_zero_window() {
+        moving_average(&[1.0], 0);
+    }
+
+    #[test]
+    fn test_carnot_efficiency() {
+        assert_eq!(carnot_efficiency(2.0, 1.0), 0.5);
+        assert_eq!(carnot_efficiency(4.0, 4.0), 0.0);
+        assert_eq!(carnot_efficiency(1.0, 0.0), 1.0);
+    }
+
+    #[test]
+    fn test_quarter_full() {
+        let h = occupation_entropy(&BitBath::from([true, false, false, false]));
+        assert!((h - 0.8112781244591328).abs() < 1e-12);
+    }
+}
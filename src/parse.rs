@@ -0,0 +1,590 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{
+    BathId, CondSwap, CyclicShift, NoOp, ProbeAndSwap, ReflectBath, Repeat, Rule, WeirdPermute,
+};
+
+/// Something wrong with a rule spec. `pos` is the byte offset in the spec
+/// where the problem starts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// The spec ended or had an unexpected character where `expected` should
+    /// have been.
+    Expected {
+        pos: usize,
+        expected: &'static str,
+    },
+    UnknownRule {
+        pos: usize,
+        name: String,
+    },
+    WrongArgCount {
+        pos: usize,
+        rule: &'static str,
+        expected: usize,
+        found: usize,
+    },
+    BadArgument {
+        pos: usize,
+        message: String,
+    },
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Expected { pos, expected } => {
+                write!(f, "at position {pos}: expected {expected}")
+            }
+            ParseError::UnknownRule { pos, name } => {
+                write!(f, "at position {pos}: unknown rule {name:?}")
+            }
+            ParseError::WrongArgCount {
+                pos,
+                rule,
+                expected,
+                found,
+            } => write!(
+                f,
+                "at position {pos}: {rule} takes {expected} argument(s), got {found}"
+            ),
+            ParseError::BadArgument { pos, message } => {
+                write!(f, "at position {pos}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Builds a rule set from a `;`-separated list of rules, e.g.
+/// `condswap; repeat(shift(hot,3),5); permute(seed=42)`, using the rules in
+/// `RuleRegistry::default()`.
+pub fn parse_rules(spec: &str) -> Result<Vec<Box<dyn Rule>>, ParseError> {
+    RuleRegistry::default().parse(spec)
+}
+
+/// Builds a rule from the arguments it was given in a spec. Use
+/// [`expect_args`] to check them against the rule's parameter names.
+pub type RuleFactory = Box<dyn Fn(&[Arg]) -> Result<Box<dyn Rule>, ParseError>>;
+
+/// Maps the rule names a spec can use to the factories that build them.
+pub struct RuleRegistry {
+    factories: HashMap<String, RuleFactory>,
+}
+
+impl RuleRegistry {
+    /// A registry that knows no rules.
+    pub fn empty() -> RuleRegistry {
+        RuleRegistry {
+            factories: HashMap::new(),
+        }
+    }
+
+    /// Makes `name` build rules with `factory`, replacing any rule already
+    /// registered under that name.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        factory: impl Fn(&[Arg]) -> Result<Box<dyn Rule>, ParseError> + 'static,
+    ) {
+        self.factories.insert(name.into(), Box::new(factory));
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+
+    /// Builds a rule set from a `;`-separated list of rules. Rule arguments
+    /// are numbers, bath names, or rules themselves, and any argument may be
+    /// given as `name=value`. An empty spec is an empty rule set.
+    pub fn parse(&self, spec: &str) -> Result<Vec<Box<dyn Rule>>, ParseError> {
+        let mut parser = Parser { spec, pos: 0 };
+        let mut rules = Vec::new();
+        parser.skip_whitespace();
+        if parser.pos == spec.len() {
+            return Ok(rules);
+        }
+        loop {
+            rules.push(parser.term()?.to_rule(self)?);
+            parser.skip_whitespace();
+            match parser.peek() {
+                None => return Ok(rules),
+                Some(';') => parser.pos += 1,
+                Some(_) => {
+                    return Err(ParseError::Expected {
+                        pos: parser.pos,
+                        expected: "`;` or end of spec",
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// The built-in rules: `condswap`, `noop`, `probeandswap(probe,
+/// battery_slot, hot_slot)`, `permute(seed)` (a `WeirdPermute`, also
+/// available as `weirdpermute`), `shift(bath, offset)`, `reflect(bath)`, and
+/// `repeat(rule, times)`. Baths are named as `BathId` displays them
+/// (`battery`, `hot`, `cold`, `extra0`, ...).
+impl Default for RuleRegistry {
+    fn default() -> Self {
+        let mut registry = RuleRegistry::empty();
+        registry.register("condswap", |args| {
+            expect_args("condswap", args, &[])?;
+            Ok(Box::new(CondSwap))
+        });
+        registry.register("noop", |args| {
+            expect_args("noop", args, &[])?;
+            Ok(Box::new(NoOp))
+        });
+        registry.register("probeandswap", |args| {
+            let [probe, battery_slot, hot_slot] =
+                expect_args("probeandswap", args, &["probe", "battery_slot", "hot_slot"])?;
+            let (probe_index, hot_index) = (probe.to_number()?, hot_slot.to_number()?);
+            if probe_index == hot_index {
+                return Err(hot_slot.bad_value("a hot slot other than the probe"));
+            }
+            Ok(Box::new(ProbeAndSwap::new(
+                probe_index,
+                battery_slot.to_number()?,
+                hot_index,
+            )))
+        });
+        for name in ["permute", "weirdpermute"] {
+            registry.register(name, move |args| {
+                let [seed] = expect_args(name, args, &["seed"])?;
+                Ok(Box::new(WeirdPermute::new(seed.to_number()?)))
+            });
+        }
+        registry.register("shift", |args| {
+            let [bath, offset] = expect_args("shift", args, &["bath", "offset"])?;
+            Ok(Box::new(CyclicShift {
+                subsystem: bath.to_bath()?,
+                offset: offset.to_number()?,
+            }))
+        });
+        registry.register("reflect", |args| {
+            let [bath] = expect_args("reflect", args, &["bath"])?;
+            Ok(Box::new(ReflectBath {
+                subsystem: bath.to_bath()?,
+            }))
+        });
+        registry.register("repeat", |args| {
+            let [inner, times] = expect_args("repeat", args, &["rule", "times"])?;
+            Ok(Box::new(Repeat {
+                inner: inner.to_rule()?,
+                times: times.to_number()?,
+            }))
+        });
+        registry
+    }
+}
+
+/// One argument passed to a rule in a spec, as handed to a [`RuleFactory`].
+pub struct Arg<'a> {
+    key: Option<&'a str>,
+    value: &'a Term,
+    registry: &'a RuleRegistry,
+}
+
+impl Arg<'_> {
+    /// The argument's name, if it was given as `name=value`.
+    pub fn key(&self) -> Option<&str> {
+        self.key
+    }
+
+    pub fn to_number<T: std::str::FromStr>(&self) -> Result<T, ParseError> {
+        if !self.value.args.is_empty() {
+            return Err(self.bad_value("a number"));
+        }
+        self.value
+            .name
+            .parse()
+            .map_err(|_| self.bad_value("a number"))
+    }
+
+    pub fn to_bath(&self) -> Result<BathId, ParseError> {
+        if !self.value.args.is_empty() {
+            return Err(self.bad_value("a bath name"));
+        }
+        Ok(match self.value.name.as_str() {
+            "battery" => BathId::Battery,
+            "hot" => BathId::Hot,
+            "cold" => BathId::Cold,
+            name => name
+                .strip_prefix("extra")
+                .and_then(|i| i.parse().ok())
+                .map(BathId::Extra)
+                .ok_or_else(|| self.bad_value("a bath name"))?,
+        })
+    }
+
+    /// Builds the argument as a rule through the same registry.
+    pub fn to_rule(&self) -> Result<Box<dyn Rule>, ParseError> {
+        self.value.to_rule(self.registry)
+    }
+
+    /// A `BadArgument` error at this argument saying what was expected
+    /// instead.
+    pub fn bad_value(&self, expected: &str) -> ParseError {
+        ParseError::BadArgument {
+            pos: self.value.pos,
+            message: format!("expected {expected}, got {:?}", self.value.name),
+        }
+    }
+}
+
+/// Checks that `args` match `names`, positionally or by key, and returns
+/// them in that order. A count mismatch is reported at the rule's position.
+pub fn expect_args<'a, 'b, const N: usize>(
+    rule: &'static str,
+    args: &'b [Arg<'a>],
+    names: &[&str; N],
+) -> Result<[&'b Arg<'a>; N], ParseError> {
+    if args.len() != N {
+        return Err(ParseError::WrongArgCount {
+            // Filled in by `Term::to_rule`, which knows where the rule is.
+            pos: 0,
+            rule,
+            expected: N,
+            found: args.len(),
+        });
+    }
+    for (arg, name) in args.iter().zip(names) {
+        if let Some(key) = arg.key {
+            if key != *name {
+                return Err(ParseError::BadArgument {
+                    pos: arg.value.pos,
+                    message: format!("expected argument {name:?} of {rule}, got {key:?}"),
+                });
+            }
+        }
+    }
+    Ok(std::array::from_fn(|i| &args[i]))
+}
+
+/// A word, optionally followed by a parenthesized argument list.
+struct Term {
+    pos: usize,
+    name: String,
+    args: Vec<ParsedArg>,
+}
+
+struct ParsedArg {
+    key: Option<String>,
+    value: Term,
+}
+
+struct Parser<'a> {
+    spec: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.spec[self.pos..].chars().next()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.pos += c.len_utf8();
+        }
+    }
+
+    fn word(&mut self) -> Result<(usize, String), ParseError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while let Some(c) = self
+            .peek()
+            .filter(|&c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+        {
+            self.pos += c.len_utf8();
+        }
+        if self.pos == start {
+            return Err(ParseError::Expected {
+                pos: start,
+                expected: "a rule or value",
+            });
+        }
+        Ok((start, self.spec[start..self.pos].to_string()))
+    }
+
+    fn term(&mut self) -> Result<Term, ParseError> {
+        let (pos, name) = self.word()?;
+        let mut args = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some('(') {
+            self.pos += 1;
+            self.skip_whitespace();
+            if self.peek() == Some(')') {
+                self.pos += 1;
+            } else {
+                loop {
+                    args.push(self.arg()?);
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(',') => self.pos += 1,
+                        Some(')') => {
+                            self.pos += 1;
+                            break;
+                        }
+                        _ => {
+                            return Err(ParseError::Expected {
+                                pos: self.pos,
+                                expected: "`,` or `)`",
+                            })
+                        }
+                    }
+                }
+            }
+        }
+        Ok(Term { pos, name, args })
+    }
+
+    fn arg(&mut self) -> Result<ParsedArg, ParseError> {
+        let value = self.term()?;
+        self.skip_whitespace();
+        if self.peek() != Some('=') {
+            return Ok(ParsedArg { key: None, value });
+        }
+        self.pos += 1;
+        if !value.args.is_empty() {
+            return Err(ParseError::BadArgument {
+                pos: value.pos,
+                message: format!("{:?} can't be used as an argument name", value.name),
+            });
+        }
+        Ok(ParsedArg {
+            key: Some(value.name),
+            value: self.term()?,
+        })
+    }
+}
+
+impl Term {
+    fn to_rule(&self, registry: &RuleRegistry) -> Result<Box<dyn Rule>, ParseError> {
+        let factory =
+            registry
+                .factories
+                .get(&self.name)
+                .ok_or_else(|| ParseError::UnknownRule {
+                    pos: self.pos,
+                    name: self.name.clone(),
+                })?;
+        let args: Vec<Arg> = self
+            .args
+            .iter()
+            .map(|arg| Arg {
+                key: arg.key.as_deref(),
+                value: &arg.value,
+                registry,
+            })
+            .collect();
+        factory(&args).map_err(|e| match e {
+            ParseError::WrongArgCount {
+                rule,
+                expected,
+                found,
+                ..
+            } => ParseError::WrongArgCount {
+                pos: self.pos,
+                rule,
+                expected,
+                found,
+            },
+            e => e,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{default_rules, SimConfig, Simulation, World};
+
+    fn world() -> World {
+        World::from_config(&SimConfig {
+            battery_size: 5,
+            bath_size: 24,
+            n_steps: 0,
+            ..SimConfig::default()
+        })
+    }
+
+    fn run(rules: Vec<Box<dyn Rule>>) -> World {
+        let mut sim = Simulation::new(world(), rules);
+        sim.step_n(100);
+        sim.into_world()
+    }
+
+    #[test]
+    fn test_default_rules() {
+        let parsed = parse_rules("condswap; permute(seed=42)").unwrap();
+        assert_eq!(run(parsed), run(default_rules(42)));
+    }
+
+    #[test]
+    fn test_composite() {
+        let spec = " condswap;repeat( shift(hot, -3), 2 ) ; reflect(bath=cold);noop;permute(7) ";
+        let parsed = parse_rules(spec).unwrap();
+        assert_eq!(parsed.len(), 5);
+        let expected: Vec<Box<dyn Rule>> = vec![
+            Box::new(CondSwap),
+            Box::new(Repeat {
+                inner: Box::new(CyclicShift {
+                    subsystem: BathId::Hot,
+                    offset: -3,
+                }),
+                times: 2,
+            }),
+            Box::new(ReflectBath {
+                subsystem: BathId::Cold,
+            }),
+            Box::new(NoOp),
+            Box::new(WeirdPermute::new(7)),
+        ];
+        assert_eq!(run(parsed), run(expected));
+
+        let mut sim = Simulation::new(world(), parse_rules(spec).unwrap());
+        sim.step_n(50);
+        for _ in 0..50 {
+            sim.step_back();
+        }
+        assert_eq!(sim.world(), &world());
+    }
+
+    #[test]
+    fn test_custom_rule() {
+        let mut registry = RuleRegistry::empty();
+        assert!(!registry.contains("flip"));
+        registry.register("flip", |args| {
+            let [bath, times] = expect_args("flip", args, &["bath", "times"])?;
+            Ok(Box::new(Repeat {
+                inner: Box::new(ReflectBath {
+                    subsystem: bath.to_bath()?,
+                }),
+                times: times.to_number()?,
+            }))
+        });
+        assert!(registry.contains("flip"));
+
+        let parsed = registry.parse("flip(cold, times=3)").unwrap();
+        let expected: Vec<Box<dyn Rule>> = vec![Box::new(ReflectBath {
+            subsystem: BathId::Cold,
+        })];
+        assert_eq!(run(parsed), run(expected));
+        assert_eq!(
+            registry.parse("condswap").err().unwrap(),
+            ParseError::UnknownRule {
+                pos: 0,
+                name: "condswap".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_probe_and_swap() {
+        let parsed = parse_rules("weirdpermute(3); probeandswap(0, 2, hot_slot=5)").unwrap();
+        let expected: Vec<Box<dyn Rule>> = vec![
+            Box::new(WeirdPermute::new(3)),
+            Box::new(ProbeAndSwap::new(0, 2, 5)),
+        ];
+        assert_eq!(run(parsed), run(expected));
+        assert_eq!(
+            parse_rules("probeandswap(4, 0, 4)")
+                .err()
+                .unwrap()
+                .to_string(),
+            "at position 19: expected a hot slot other than the probe, got \"4\""
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        assert!(parse_rules("  ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = |spec: &str| parse_rules(spec).err().unwrap();
+        assert_eq!(
+            err("condswap; condswop"),
+            ParseError::UnknownRule {
+                pos: 10,
+                name: "condswop".to_string(),
+            }
+        );
+        assert_eq!(
+            err("shift(hot)"),
+            ParseError::WrongArgCount {
+                pos: 0,
+                rule: "shift",
+                expected: 2,
+                found: 1,
+            }
+        );
+        assert_eq!(
+            err("noop; shift(hot)"),
+            ParseError::WrongArgCount {
+                pos: 6,
+                rule: "shift",
+                expected: 2,
+                found: 1,
+            }
+        );
+        assert_eq!(
+            err("shift(tepid, 1)").to_string(),
+            "at position 6: expected a bath name, got \"tepid\""
+        );
+        assert_eq!(
+            err("permute(seed=x)").to_string(),
+            "at position 13: expected a number, got \"x\""
+        );
+        assert_eq!(
+            err("permute(offset=1)").to_string(),
+            "at position 15: expected argument \"seed\" of permute, got \"offset\""
+        );
+        assert_eq!(
+            err("condswap;;noop"),
+            ParseError::Expected {
+                pos: 9,
+                expected: "a rule or value",
+            }
+        );
+        assert_eq!(
+            err("repeat(condswap, 2"),
+            ParseError::Expected {
+                pos: 18,
+                expected: "`,` or `)`",
+            }
+        );
+        assert_eq!(
+            err("noop noop"),
+            ParseError::Expected {
+                pos: 5,
+                expected: "`;` or end of spec",
+            }
+        );
+    }
+
+    #[test]
+    fn test_non_ascii() {
+        assert_eq!(parse_rules("condswap;\u{a0}condswap").unwrap().len(), 2);
+        assert_eq!(parse_rules("\u{3000}noop\u{2003}").unwrap().len(), 1);
+        assert_eq!(
+            parse_rules("condswap; cöndswap").err().unwrap(),
+            ParseError::UnknownRule {
+                pos: 10,
+                name: "c".to_string(),
+            }
+        );
+        assert_eq!(
+            parse_rules("noop;\u{a0}é").err().unwrap(),
+            ParseError::Expected {
+                pos: 7,
+                expected: "a rule or value",
+            }
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use crate::World;
+
+/// The most cells a world can have for `state_key` to encode it.
+pub const MAX_STATE_CELLS: usize = 24;
+
+/// Packs a world's cells into a number: subsystem by subsystem, cell `i`
+/// overall is bit `i`. `t` is ignored, and keys only mean the same state
+/// for worlds with the same subsystem lengths. `None` if the world has more
+/// than `MAX_STATE_CELLS` cells.
+pub fn state_key(world: &World) -> Option<u64> {
+    let mut key = 0;
+    let mut bit = 0;
+    for bath in world.subsystems() {
+        if bit + bath.cells.len() > MAX_STATE_CELLS {
+            return None;
+        }
+        for occupied in bath.cells.iter() {
+            key |= (occupied as u64) << bit;
+            bit += 1;
+        }
+    }
+    Some(key)
+}
+
+/// How often each state of a small world has come up, keyed by `state_key`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateCounter {
+    counts: HashMap<u64, u64>,
+}
+
+impl StateCounter {
+    pub fn new() -> StateCounter {
+        StateCounter::default()
+    }
+
+    /// Panics if the world has more than `MAX_STATE_CELLS` cells.
+    pub fn record(&mut self, world: &World) {
+        let key = state_key(world).unwrap_or_else(|| {
+            panic!("StateCounter only handles worlds of at most {MAX_STATE_CELLS} cells")
+        });
+        *self.counts.entry(key).or_default() += 1;
+    }
+
+    /// How many times the state with this key has been recorded.
+    pub fn count(&self, key: u64) -> u64 {
+        self.counts.get(&key).copied().unwrap_or(0)
+    }
+
+    /// How many different states have been recorded.
+    pub fn distinct(&self) -> usize {
+        self.counts.len()
+    }
+
+    /// The key and count of the most-recorded state, taking the smallest key
+    /// on a tie; `None` if nothing has been recorded.
+    pub fn most_visited(&self) -> Option<(u64, u64)> {
+        self.counts
+            .iter()
+            .map(|(&key, &count)| (key, count))
+            .max_by_key(|&(key, count)| (count, std::cmp::Reverse(key)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{BathId, BitBath, CyclicShift, Rule};
+
+    #[test]
+    fn test_state_key() {
+        let world = World::classic([true, false], [false, true, true], [true]);
+        assert_eq!(state_key(&world), Some(0b111001));
+        assert_eq!(state_key(&World::default()), Some(0));
+        assert!(state_key(&World::new(4, 10)).is_some());
+        assert_eq!(state_key(&World::new(5, 10)), None);
+    }
+
+    #[test]
+    fn test_cyclic_shift() {
+        let mut world = World::classic(
+            [true, false, false, false],
+            BitBath::new(0),
+            BitBath::new(0),
+        );
+        let rule = CyclicShift {
+            subsystem: BathId::Battery,
+            offset: 1,
+        };
+        let mut counter = StateCounter::new();
+        counter.record(&world);
+        for _ in 0..10 {
+            rule.step(&mut world);
+            world.t += 1;
+            counter.record(&world);
+        }
+        // The bit cycles through the four cells: 1, 2, 4, 8, 1, ...
+        assert_eq!(counter.distinct(), 4);
+        assert_eq!([1, 2, 4, 8].map(|key| counter.count(key)), [3, 3, 3, 2]);
+        assert_eq!(counter.count(3), 0);
+        assert_eq!(counter.most_visited(), Some((1, 3)));
+        assert_eq!(StateCounter::new().most_visited(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "at most 24 cells")]
+    fn test_too_big() {
+        StateCounter::new().record(&World::new(5, 10));
+    }
+}
@@ -0,0 +1,750 @@
+use std::{collections::VecDeque, time::Instant};
+
+use crate::{
+    default_rules,
+    heat::{energies, HeatLedger},
+    histogram::{EnergyStats, Histogram},
+    BathId, Rule, RuleError, SimConfig, Topology, World,
+};
+
+#[cfg(feature = "serde")]
+mod checkpoint;
+mod report;
+
+#[cfg(feature = "serde")]
+pub use checkpoint::CheckpointError;
+pub use report::RunReport;
+
+type Invariant = Box<dyn Fn(&World) -> bool>;
+
+/// A world together with the rules that drive it, keeping `t` in step with
+/// each application of the rules.
+pub struct Simulation {
+    world: World,
+    rules: Vec<Box<dyn Rule>>,
+    inv_rules: Box<dyn Rule>,
+    work_extracted: i64,
+    heat: HeatLedger,
+    battery_histogram: Histogram,
+    energy_stats: EnergyStats,
+    invariants: Vec<Invariant>,
+    /// The worlds before the most recent forward steps, newest last, for
+    /// `undo`; at most `history_depth` of them.
+    history: VecDeque<World>,
+    history_depth: usize,
+    /// When and at what `t` the simulation was constructed, for `report`.
+    start_t: i64,
+    started: Instant,
+}
+
+/// An invariant for `Simulation::with_invariant` that holds while the total
+/// energy stays what it is in `world`.
+pub fn conserve_total_energy(world: &World) -> impl Fn(&World) -> bool {
+    let total = world.total_energy();
+    move |w| w.total_energy() == total
+}
+
+impl Simulation {
+    /// Panics if the rules don't fit the world; see `try_new`.
+    pub fn new(world: World, rules: Vec<Box<dyn Rule>>) -> Simulation {
+        Simulation::try_new(world, rules).unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Like `new`, but checks the rules against the world with
+    /// `Rule::validate` first instead of panicking.
+    pub fn try_new(world: World, rules: Vec<Box<dyn Rule>>) -> Result<Simulation, RuleError> {
+        rules.validate(&world)?;
+        let inv_rules = rules.inverse();
+        let battery_histogram = Histogram::new(world.battery().len());
+        let start_t = world.t;
+        Ok(Simulation {
+            world,
+            rules,
+            inv_rules,
+            work_extracted: 0,
+            heat: HeatLedger::new(),
+            battery_histogram,
+            energy_stats: EnergyStats::new(),
+            invariants: Vec::new(),
+            history: VecDeque::new(),
+            history_depth: 0,
+            start_t,
+            started: Instant::now(),
+        })
+    }
+
+    /// Like `try_new`, but also rejects rule sets that couple subsystems
+    /// `topology` keeps apart.
+    pub fn try_with_topology(
+        world: World,
+        rules: Vec<Box<dyn Rule>>,
+        topology: &Topology,
+    ) -> Result<Simulation, RuleError> {
+        topology.check(&rules, &world)?;
+        Simulation::try_new(world, rules)
+    }
+
+    /// The default rule set with `seed` on the world `config` describes, as
+    /// `main` runs it.
+    pub fn seeded(config: &SimConfig, seed: u64) -> Simulation {
+        Simulation::new(World::from_config(config), default_rules(seed))
+    }
+
+    /// Like `new`, but with the world's clock set to `start_t` first. Rules
+    /// such as `WeirdPermute` depend on `t`, so the same world evolves
+    /// differently from different starting times.
+    pub fn new_at(mut world: World, rules: Vec<Box<dyn Rule>>, start_t: i64) -> Simulation {
+        world.t = start_t;
+        Simulation::new(world, rules)
+    }
+
+    /// Adds a check that must hold before and after every step. Checks only
+    /// run with the `check-invariants` feature; a failing one panics.
+    pub fn with_invariant(mut self, invariant: impl Fn(&World) -> bool + 'static) -> Self {
+        self.invariants.push(Box::new(invariant));
+        self
+    }
+
+    /// Keeps the worlds from before the last `depth` forward steps so that
+    /// `undo` can restore them. Each is a full copy, so this costs up to
+    /// `depth` times the world's size in memory (about one bit per cell),
+    /// plus a clone per step; the default depth of zero keeps nothing.
+    pub fn with_history(mut self, depth: usize) -> Self {
+        self.history_depth = depth;
+        let excess = self.history.len().saturating_sub(depth);
+        self.history.drain(..excess);
+        self
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// The seed the rule set runs on, e.g. `seeded`'s `WeirdPermute` seed:
+    /// the first that any rule reports through `Rule::seed`. Passing it back
+    /// to `seeded` with the same config replays the run.
+    pub fn seed(&self) -> Option<u64> {
+        self.rules.seed()
+    }
+
+    pub fn into_world(self) -> World {
+        self.world
+    }
+
+    /// Net quanta gained by the battery over all steps since construction
+    /// or the last `reset_work`. Stepping back subtracts them again.
+    pub fn work_extracted(&self) -> i64 {
+        self.work_extracted
+    }
+
+    pub fn reset_work(&mut self) {
+        self.work_extracted = 0;
+    }
+
+    /// Per-subsystem energy flows since construction or the last
+    /// `reset_heat`. Like `work_extracted`, stepping back undoes them.
+    pub fn heat(&self) -> &HeatLedger {
+        &self.heat
+    }
+
+    pub fn reset_heat(&mut self) {
+        self.heat = HeatLedger::new();
+    }
+
+    /// Work extracted per unit of heat the hot bath has released, or `None`
+    /// if it hasn't released any.
+    pub fn efficiency(&self) -> Option<f64> {
+        let heat = self.heat.hot_released();
+        if heat == 0 {
+            return None;
+        }
+        Some(self.work_extracted as f64 / heat as f64)
+    }
+
+    /// How often each battery energy has come up after a step. Unlike work
+    /// and heat, stepping back leaves it alone.
+    pub fn battery_histogram(&self) -> &Histogram {
+        &self.battery_histogram
+    }
+
+    /// Running mean and variance of the total energy after each step. Like
+    /// the battery histogram, only forward steps count.
+    pub fn energy_stats(&self) -> &EnergyStats {
+        &self.energy_stats
+    }
+
+    pub fn step(&mut self) {
+        self.check_invariants();
+        if self.history_depth > 0 {
+            if self.history.len() == self.history_depth {
+                self.history.pop_front();
+            }
+            self.history.push_back(self.world.clone());
+        }
+        let before = energies(&self.world);
+        self.rules.step(&mut self.world);
+        self.world.t += 1;
+        self.record(&before);
+        self.battery_histogram.record(self.world.battery_energy());
+        self.energy_stats.record(self.world.total_energy() as f64);
+        self.check_invariants();
+    }
+
+    pub fn step_n(&mut self, n: u64) {
+        for _ in 0..n {
+            self.step();
+        }
+    }
+
+    /// Like `step_n`, but calls `on_progress` with the number of steps taken
+    /// so far after every `stride` steps (`n / 100` gives percent updates),
+    /// and after the last step. A `stride` of zero is treated as one.
+    pub fn step_n_with_progress(&mut self, n: u64, stride: u64, mut on_progress: impl FnMut(u64)) {
+        let stride = stride.max(1);
+        let mut done = 0;
+        while done < n {
+            let chunk = stride.min(n - done);
+            self.step_n(chunk);
+            done += chunk;
+            on_progress(done);
+        }
+    }
+
+    /// Undoes one `step` by applying the inverse rules.
+    pub fn step_back(&mut self) {
+        self.check_invariants();
+        let before = energies(&self.world);
+        self.inv_rules.step(&mut self.world);
+        self.world.t -= 1;
+        self.record(&before);
+        // The inverse just recreated the newest saved world.
+        self.history.pop_back();
+        self.check_invariants();
+    }
+
+    /// Restores the world from before the most recent forward step still in
+    /// the history (see `with_history`), without running any inverse rules.
+    /// Work and heat are wound back as by `step_back`. Returns false, doing
+    /// nothing, if the history is empty.
+    pub fn undo(&mut self) -> bool {
+        let Some(previous) = self.history.pop_back() else {
+            return false;
+        };
+        self.check_invariants();
+        let before = energies(&self.world);
+        self.world = previous;
+        self.record(&before);
+        self.check_invariants();
+        true
+    }
+
+    fn check_invariants(&self) {
+        if !cfg!(feature = "check-invariants") {
+            return;
+        }
+        for (i, invariant) in self.invariants.iter().enumerate() {
+            assert!(
+                invariant(&self.world),
+                "invariant {i} violated at t={}:\n{:#}",
+                self.world.t,
+                self.world
+            );
+        }
+    }
+
+    fn record(&mut self, before: &[usize]) {
+        self.work_extracted +=
+            self.world.battery_energy() as i64 - before[BathId::Battery.index()] as i64;
+        self.heat.record(before, &self.world);
+    }
+
+    /// Steps `warmup` times to let transients die out, then `measure` more
+    /// times, passing the world after each of those to `observe`. The
+    /// simulation's own tallies, like `battery_histogram`, still count the
+    /// warm-up steps.
+    pub fn run_with_warmup(&mut self, warmup: u64, measure: u64, mut observe: impl FnMut(&World)) {
+        self.step_n(warmup);
+        self.fold(measure, (), |(), world| observe(world));
+    }
+
+    /// Steps `n_steps` times, threading an accumulator through `f` with the
+    /// world after each step (not the current one), and returns the result;
+    /// e.g. a tally of some observable without keeping a trajectory.
+    pub fn fold<B>(&mut self, n_steps: u64, init: B, mut f: impl FnMut(B, &World) -> B) -> B {
+        let mut acc = init;
+        for _ in 0..n_steps {
+            self.step();
+            acc = f(acc, &self.world);
+        }
+        acc
+    }
+
+    /// Steps until the battery energy levels off, and returns how many steps
+    /// that took, or `None` if it hadn't after `max_steps`.
+    ///
+    /// "Levelled off" means the mean battery energy over the last `window`
+    /// steps is within `tol` of the mean over the `window` steps before
+    /// those.
+    pub fn run_until_steady(&mut self, window: usize, tol: f64, max_steps: u64) -> Option<u64> {
+        assert!(window > 0, "window must be positive");
+        let mut recent = VecDeque::with_capacity(2 * window);
+        for n in 1..=max_steps {
+            self.step();
+            if recent.len() == 2 * window {
+                recent.pop_front();
+            }
+            recent.push_back(self.world.battery_energy() as f64);
+            if recent.len() == 2 * window {
+                let older: f64 = recent.iter().take(window).sum();
+                let newer: f64 = recent.iter().skip(window).sum();
+                if (newer - older).abs() / window as f64 <= tol {
+                    return Some(n);
+                }
+            }
+        }
+        None
+    }
+
+    /// An endless iterator that steps the simulation and yields the world
+    /// after each step (not including the current one).
+    pub fn iter(&mut self) -> StateIter<'_> {
+        StateIter {
+            sim: self,
+            stride: 1,
+        }
+    }
+}
+
+/// Yields an owned clone of the world per item, so each one costs an
+/// allocation the size of the world. Use `step_stride` to skip states you
+/// don't need rather than cloning and discarding them.
+pub struct StateIter<'a> {
+    sim: &'a mut Simulation,
+    stride: u64,
+}
+
+impl StateIter<'_> {
+    /// Steps `stride` times between yielded states.
+    pub fn step_stride(mut self, stride: u64) -> Self {
+        assert!(stride > 0, "stride must be positive");
+        self.stride = stride;
+        self
+    }
+}
+
+impl Iterator for StateIter<'_> {
+    type Item = World;
+
+    fn next(&mut self) -> Option<World> {
+        self.sim.step_n(self.stride);
+        Some(self.sim.world().clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{CondSwap, ControlledSwap, NoOp, ProbeAndSwap, WeirdPermute};
+
+    /// Moves one quantum from the hot bath into the battery per step, until
+    /// one of them runs out. Not reversible; only for tests.
+    #[derive(Clone)]
+    struct Charge;
+
+    impl Rule for Charge {
+        fn step(&self, world: &mut World) {
+            let hot = world.hot_bath().iter().position(|b| b);
+            let battery = world.battery().iter().position(|b| !b);
+            if let (Some(h), Some(b)) = (hot, battery) {
+                world.swap_cells((BathId::Hot, h), (BathId::Battery, b));
+            }
+        }
+
+        fn inverse(&self) -> Box<dyn Rule> {
+            Box::new(NoOp)
+        }
+
+        fn clone_box(&self) -> Box<dyn Rule> {
+            Box::new(self.clone())
+        }
+    }
+
+    fn sim() -> Simulation {
+        let world = World::builder()
+            .battery([false, false, true, false])
+            .hot_bath_half_full(12)
+            .cold_bath([false; 12])
+            .build();
+        Simulation::new(
+            world,
+            vec![
+                Box::new(CondSwap),
+                Box::new(ProbeAndSwap::default()),
+                Box::new(WeirdPermute::new(11)),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_step_then_step_back() {
+        let mut sim = sim();
+        let start = sim.world().clone();
+        sim.step();
+        assert_eq!(sim.world().t, 1);
+        assert_ne!(sim.world(), &start);
+        sim.step_back();
+        assert_eq!(sim.world(), &start);
+    }
+
+    #[test]
+    fn test_step_n_with_progress() {
+        let mut calls = Vec::new();
+        let mut sim = sim();
+        sim.step_n_with_progress(1000, 1000 / 100, |done| calls.push(done));
+        assert_eq!(calls.len(), 100);
+        assert_eq!(calls[..3], [10, 20, 30]);
+        assert_eq!(sim.world().t, 1000);
+
+        let mut plain = self::sim();
+        plain.step_n(1000);
+        assert_eq!(sim.world(), plain.world());
+
+        calls.clear();
+        sim.step_n_with_progress(25, 10, |done| calls.push(done));
+        assert_eq!(calls, [10, 20, 25]);
+        calls.clear();
+        sim.step_n_with_progress(3, 0, |done| calls.push(done));
+        assert_eq!(calls, [1, 2, 3]);
+    }
+
+    #[test]
+    fn test_run_with_warmup() {
+        let mut ts = Vec::new();
+        let mut sim = sim();
+        sim.run_with_warmup(5, 3, |w| ts.push(w.t));
+        assert_eq!(ts, [6, 7, 8]);
+        assert_eq!(sim.world().t, 8);
+
+        let mut stepped = self::sim();
+        stepped.step_n(8);
+        assert_eq!(sim.world(), stepped.world());
+    }
+
+    #[test]
+    fn test_seed_replays_run() {
+        let config = SimConfig {
+            battery_size: 8,
+            bath_size: 20,
+            ..SimConfig::default()
+        };
+        let mut sim = Simulation::seeded(&config, 1234);
+        assert_eq!(sim.seed(), Some(1234));
+        sim.step_n(200);
+        let mut replay = Simulation::seeded(&config, sim.seed().unwrap());
+        replay.step_n(200);
+        assert_eq!(replay.world(), sim.world());
+        assert_eq!(self::sim().seed(), Some(11));
+        assert_eq!(
+            Simulation::new(World::default(), vec![Box::new(NoOp)]).seed(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_topology() {
+        let topology = Topology::new().allow(BathId::Battery, BathId::Hot);
+        let hot_cold = ControlledSwap::new((BathId::Hot, 1), (BathId::Hot, 0), (BathId::Cold, 0));
+        let err = Simulation::try_with_topology(
+            World::new(4, 8),
+            vec![Box::new(ProbeAndSwap::default()), Box::new(hot_cold)],
+            &topology,
+        )
+        .err()
+        .unwrap();
+        assert_eq!(
+            err,
+            RuleError::Uncoupled {
+                position: 1,
+                a: BathId::Hot,
+                b: BathId::Cold,
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "rule 1 couples hot and cold, which the topology doesn't allow"
+        );
+        assert!(Simulation::try_with_topology(
+            World::new(4, 8),
+            vec![Box::new(ProbeAndSwap::default())],
+            &topology,
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_undo() {
+        let start = sim();
+        let mut sim = self::sim().with_history(10);
+        let mut worlds = vec![sim.world().clone()];
+        for _ in 0..6 {
+            sim.step();
+            worlds.push(sim.world().clone());
+        }
+        while let Some(expected) = worlds.pop() {
+            assert_eq!(sim.world(), &expected);
+            sim.undo();
+        }
+        assert_eq!(sim.world(), start.world());
+        assert_eq!(sim.work_extracted(), 0);
+        assert!(!sim.undo());
+    }
+
+    #[test]
+    fn test_undo_depth() {
+        let mut sim = sim().with_history(3);
+        sim.step_n(5);
+        let mut expected = self::sim();
+        expected.step_n(2);
+        assert!(sim.undo() && sim.undo() && sim.undo());
+        assert!(!sim.undo());
+        assert_eq!(sim.world(), expected.world());
+
+        let mut sim = self::sim();
+        sim.step();
+        assert!(!sim.undo());
+
+        // Shrinking the depth keeps the newest worlds.
+        let mut sim = self::sim().with_history(4);
+        sim.step_n(4);
+        let mut sim = sim.with_history(1);
+        let mut expected = self::sim();
+        expected.step_n(3);
+        assert!(sim.undo());
+        assert!(!sim.undo());
+        assert_eq!(sim.world(), expected.world());
+    }
+
+    #[test]
+    fn test_undo_after_step_back() {
+        let mut sim = sim().with_history(5);
+        sim.step_n(3);
+        sim.step_back();
+        let mut expected = self::sim();
+        expected.step();
+        assert!(sim.undo());
+        assert_eq!(sim.world(), expected.world());
+    }
+
+    #[test]
+    fn test_fold() {
+        let world = World::builder()
+            .battery([false; 4])
+            .hot_bath([true; 8])
+            .build();
+        let mut sim = Simulation::new(world, vec![Box::new(Charge)]);
+        // The battery reads 1, 2, 3, 4, 4, 4 after each step.
+        let over_half = sim.fold(6, 0, |n, w| n + (w.battery_energy() * 2 > 4) as u64);
+        assert_eq!(over_half, 4);
+        assert_eq!(sim.world().t, 6);
+
+        let mut sim = self::sim();
+        let ts = sim.fold(3, Vec::new(), |mut ts, w| {
+            ts.push(w.t);
+            ts
+        });
+        assert_eq!(ts, [1, 2, 3]);
+        assert_eq!(sim.fold(0, 7, |_, _| 0), 7);
+    }
+
+    #[test]
+    fn test_work_extracted() {
+        let world = World::builder()
+            .battery([false; 5])
+            .hot_bath([true; 8])
+            .build();
+        let mut sim = Simulation::new(world, vec![Box::new(Charge)]);
+        sim.step_n(3);
+        assert_eq!(sim.work_extracted(), 3);
+        sim.step_n(10);
+        assert_eq!(sim.work_extracted(), 5);
+        assert_eq!(sim.work_extracted(), sim.world().battery_energy() as i64);
+        sim.reset_work();
+        assert_eq!(sim.work_extracted(), 0);
+    }
+
+    #[test]
+    fn test_work_undone_by_step_back() {
+        let mut sim = sim();
+        sim.step_n(40);
+        for _ in 0..40 {
+            sim.step_back();
+        }
+        assert_eq!(sim.work_extracted(), 0);
+    }
+
+    #[test]
+    fn test_efficiency() {
+        // CondSwap fires once, moving one hot quantum to the battery and one
+        // to the cold bath, and then never again.
+        let world = World::builder()
+            .battery([false, false])
+            .hot_bath([true, true, false])
+            .cold_bath([false])
+            .build();
+        let mut sim = Simulation::new(world, vec![Box::new(CondSwap)]);
+        assert_eq!(sim.efficiency(), None);
+        sim.step();
+        assert_eq!(sim.efficiency(), Some(0.5));
+
+        let world = World::builder()
+            .battery([false; 3])
+            .hot_bath([true; 5])
+            .build();
+        let mut sim = Simulation::new(world, vec![Box::new(Charge)]);
+        sim.step_n(10);
+        assert_eq!(sim.work_extracted(), 3);
+        assert_eq!(sim.efficiency(), Some(1.0));
+    }
+
+    #[test]
+    fn test_invariant_holds() {
+        let world = sim().into_world();
+        let mut sim = sim().with_invariant(conserve_total_energy(&world));
+        sim.step_n(50);
+        sim.step_back();
+    }
+
+    #[test]
+    #[cfg(feature = "check-invariants")]
+    #[should_panic(expected = "invariant 1 violated at t=1")]
+    fn test_invariant_violated() {
+        // Bypasses `Permute::new`'s validation to copy hot cell 0 over the
+        // whole bath.
+        let world = World::builder().hot_bath([true, false, false]).build();
+        let bad = crate::Permute {
+            permutations: vec![vec![], vec![0, 0, 0]],
+        };
+        let mut sim = Simulation::new(world.clone(), vec![Box::new(bad)])
+            .with_invariant(|w| w.t < 100)
+            .with_invariant(conserve_total_energy(&world));
+        sim.step();
+    }
+
+    #[test]
+    fn test_new_at_negative_t() {
+        let start = sim().into_world();
+        let mut sim = Simulation::new_at(start.clone(), crate::default_rules(3), -500);
+        assert_eq!(sim.world().t, -500);
+        let at_start = sim.world().clone();
+        sim.step_n(1000);
+        assert_eq!(sim.world().t, 500);
+        assert_ne!(sim.world(), &at_start);
+        for _ in 0..1000 {
+            sim.step_back();
+        }
+        assert_eq!(sim.world(), &at_start);
+        assert_eq!(sim.work_extracted(), 0);
+
+        let mut from_zero = Simulation::new(start, crate::default_rules(3));
+        from_zero.step_n(10);
+        sim.step_n(10);
+        assert_ne!(sim.world().subsystems(), from_zero.world().subsystems());
+    }
+
+    #[test]
+    fn test_seeded() {
+        let config = SimConfig {
+            battery_size: 5,
+            bath_size: 40,
+            n_steps: 0,
+            ..SimConfig::default()
+        };
+        let (mut a, mut b) = (
+            Simulation::seeded(&config, 9),
+            Simulation::seeded(&config, 9),
+        );
+        a.step_n(300);
+        b.step_n(300);
+        assert_eq!(a.world(), b.world());
+        let mut c = Simulation::seeded(&config, 10);
+        c.step_n(300);
+        assert_ne!(a.world(), c.world());
+    }
+
+    #[test]
+    fn test_run_until_steady() {
+        // Charge fills the 5-cell battery in 5 steps; the next 8 see it full.
+        let world = World::builder()
+            .battery([false; 5])
+            .hot_bath([true; 8])
+            .build();
+        let mut sim = Simulation::new(world, vec![Box::new(Charge)]);
+        assert_eq!(sim.run_until_steady(4, 0.0, 1000), Some(12));
+        assert_eq!(sim.world().t, 12);
+    }
+
+    #[test]
+    fn test_run_until_steady_gives_up() {
+        // CondSwap flips the battery between 0 and 1 every step, so windows
+        // of odd length never agree.
+        let world = World::classic([false], [true, true], [false]);
+        let mut sim = Simulation::new(world, vec![Box::new(CondSwap)]);
+        assert_eq!(sim.run_until_steady(3, 0.1, 50), None);
+        assert_eq!(sim.world().t, 50);
+    }
+
+    #[test]
+    fn test_try_new_too_small() {
+        let world = World::classic([false], [true], []);
+        let err = Simulation::try_new(world, vec![Box::new(CondSwap)]).err();
+        assert_eq!(
+            err,
+            Some(RuleError::OutOfRange {
+                rule: "CondSwap",
+                subsystem: BathId::Hot,
+                index: 1,
+                len: 1,
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "CondSwap uses hot cell 1")]
+    fn test_new_too_small() {
+        Simulation::new(
+            World::classic([false], [true], []),
+            vec![Box::new(CondSwap)],
+        );
+    }
+
+    #[test]
+    fn test_iter() {
+        let states: Vec<World> = sim().iter().take(4).collect();
+        let mut manual = sim();
+        for state in &states {
+            manual.step();
+            assert_eq!(state, manual.world());
+        }
+        assert_eq!(states.last().unwrap().t, 4);
+    }
+
+    #[test]
+    fn test_iter_stride() {
+        let mut sim = sim();
+        let ts: Vec<i64> = sim.iter().step_stride(3).take(3).map(|w| w.t).collect();
+        assert_eq!(ts, vec![3, 6, 9]);
+        assert_eq!(sim.world().t, 9);
+    }
+
+    #[test]
+    fn test_step_n_then_step_back() {
+        let mut sim = sim();
+        let start = sim.world().clone();
+        sim.step_n(25);
+        assert_eq!(sim.world().t, 25);
+        for _ in 0..25 {
+            sim.step_back();
+        }
+        assert_eq!(sim.world(), &start);
+    }
+}
@@ -0,0 +1,191 @@
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::Simulation;
+use crate::{HeatLedger, Rule, RuleError, World};
+
+#[derive(Serialize, Deserialize)]
+struct Checkpoint {
+    world: World,
+    seed: Option<u64>,
+    work_extracted: i64,
+    heat: HeatLedger,
+    start_t: i64,
+}
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    Format(serde_json::Error),
+    /// The rules passed to `load_checkpoint` don't use the seed the
+    /// checkpointed run did, so continuing would diverge from it.
+    SeedMismatch {
+        saved: Option<u64>,
+        supplied: Option<u64>,
+    },
+    /// The rules passed to `load_checkpoint` don't fit the saved world.
+    Rule(RuleError),
+}
+
+impl Display for CheckpointError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheckpointError::Io(e) => write!(f, "checkpoint I/O failed: {e}"),
+            CheckpointError::Format(e) => write!(f, "malformed checkpoint: {e}"),
+            CheckpointError::SeedMismatch { saved, supplied } => write!(
+                f,
+                "checkpoint was saved with seed {saved:?} but the rules use seed {supplied:?}"
+            ),
+            CheckpointError::Rule(e) => write!(f, "checkpointed world doesn't fit the rules: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl From<io::Error> for CheckpointError {
+    fn from(e: io::Error) -> Self {
+        CheckpointError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for CheckpointError {
+    fn from(e: serde_json::Error) -> Self {
+        CheckpointError::Format(e)
+    }
+}
+
+impl From<RuleError> for CheckpointError {
+    fn from(e: RuleError) -> Self {
+        CheckpointError::Rule(e)
+    }
+}
+
+impl Simulation {
+    /// Writes the world (including `t`), the rules' seed, the work counter,
+    /// the heat ledger and the run's starting `t` to `path` as JSON. The
+    /// rules themselves aren't saved, nor are the statistics and history
+    /// that start over on resuming.
+    pub fn save_checkpoint(&self, path: impl AsRef<Path>) -> Result<(), CheckpointError> {
+        let checkpoint = Checkpoint {
+            world: self.world.clone(),
+            seed: self.rules.seed(),
+            work_extracted: self.work_extracted,
+            heat: self.heat.clone(),
+            start_t: self.start_t,
+        };
+        let mut out = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut out, &checkpoint)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Resumes a run saved by `save_checkpoint`. `rules` must be the same
+    /// rule set the run was using; a different seed is rejected, as are rules
+    /// that don't fit the saved world.
+    pub fn load_checkpoint(
+        path: impl AsRef<Path>,
+        rules: Vec<Box<dyn Rule>>,
+    ) -> Result<Simulation, CheckpointError> {
+        let checkpoint: Checkpoint = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        if rules.seed() != checkpoint.seed {
+            return Err(CheckpointError::SeedMismatch {
+                saved: checkpoint.seed,
+                supplied: rules.seed(),
+            });
+        }
+        let mut sim = Simulation::try_new(checkpoint.world, rules)?;
+        sim.work_extracted = checkpoint.work_extracted;
+        sim.heat = checkpoint.heat;
+        sim.start_t = checkpoint.start_t;
+        Ok(sim)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{default_rules, BathId, CyclicShift, SimConfig, WeirdPermute};
+
+    fn path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!(
+            "generalized-heat-engine-{}-{name}.json",
+            std::process::id()
+        ))
+    }
+
+    fn sim(seed: u64) -> Simulation {
+        let config = SimConfig {
+            battery_size: 6,
+            bath_size: 30,
+            n_steps: 0,
+            ..SimConfig::default()
+        };
+        Simulation::new(World::from_config(&config), default_rules(seed))
+    }
+
+    #[test]
+    fn test_resume_matches_uninterrupted() {
+        let mut uninterrupted = sim(77);
+        uninterrupted.step_n(1000);
+
+        let path = path("resume");
+        let mut first_half = sim(77);
+        first_half.step_n(500);
+        first_half.save_checkpoint(&path).unwrap();
+        let mut resumed = Simulation::load_checkpoint(&path, default_rules(77)).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        resumed.step_n(500);
+
+        assert_eq!(resumed.world(), uninterrupted.world());
+        assert_eq!(resumed.work_extracted(), uninterrupted.work_extracted());
+        assert_eq!(resumed.heat(), uninterrupted.heat());
+        let (resumed, uninterrupted) = (resumed.report(), uninterrupted.report());
+        assert_eq!(resumed.start_t, uninterrupted.start_t);
+        assert_eq!(resumed.net_heat, uninterrupted.net_heat);
+        assert_eq!(resumed.efficiency, uninterrupted.efficiency);
+    }
+
+    #[test]
+    fn test_rules_dont_fit() {
+        let path = path("misfit");
+        sim(1).save_checkpoint(&path).unwrap();
+        let rules: Vec<Box<dyn Rule>> = vec![
+            Box::new(WeirdPermute::new(1)),
+            Box::new(CyclicShift {
+                subsystem: BathId::Extra(0),
+                offset: 1,
+            }),
+        ];
+        let result = Simulation::load_checkpoint(&path, rules);
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            result,
+            Err(CheckpointError::Rule(RuleError::MissingSubsystem {
+                rule: "CyclicShift",
+                subsystem: BathId::Extra(0),
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_seed_mismatch() {
+        let path = path("mismatch");
+        sim(1).save_checkpoint(&path).unwrap();
+        let result = Simulation::load_checkpoint(&path, default_rules(2));
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            result,
+            Err(CheckpointError::SeedMismatch {
+                saved: Some(1),
+                supplied: Some(2)
+            })
+        ));
+    }
+}
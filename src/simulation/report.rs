@@ -0,0 +1,125 @@
+use std::{fmt::Display, time::Duration};
+
+use super::Simulation;
+
+/// A summary of a run so far; see `Simulation::report`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunReport {
+    /// `t` when the run began, before any checkpoint it was resumed from.
+    pub start_t: i64,
+    pub final_t: i64,
+    /// Each subsystem's name, occupied cells, and length, in `BathId::index`
+    /// order.
+    pub occupations: Vec<(String, usize, usize)>,
+    pub work_extracted: i64,
+    /// The net energy each subsystem gained, in the same order as
+    /// `occupations`; a bath that gave up heat is negative.
+    pub net_heat: Vec<i64>,
+    pub efficiency: Option<f64>,
+    /// Wall time since the simulation was constructed.
+    pub elapsed: Duration,
+}
+
+impl Display for RunReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "t: {} -> {} in {:.2?}",
+            self.start_t, self.final_t, self.elapsed
+        )?;
+        for ((name, occupied, len), heat) in self.occupations.iter().zip(&self.net_heat) {
+            writeln!(f, "{name}: {occupied}/{len} occupied, net {heat:+}")?;
+        }
+        writeln!(f, "work extracted: {}", self.work_extracted)?;
+        match self.efficiency {
+            Some(efficiency) => write!(f, "efficiency: {efficiency:.4}"),
+            None => write!(f, "efficiency: n/a"),
+        }
+    }
+}
+
+impl Simulation {
+    pub fn report(&self) -> RunReport {
+        let world = &self.world;
+        RunReport {
+            start_t: self.start_t,
+            final_t: world.t,
+            occupations: world
+                .subsystems()
+                .iter()
+                .map(|s| (s.name.clone(), s.cells.count_ones(), s.cells.len()))
+                .collect(),
+            work_extracted: self.work_extracted,
+            net_heat: world.ids().map(|id| self.heat.total(id)).collect(),
+            efficiency: self.efficiency(),
+            elapsed: self.started.elapsed(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{CondSwap, World};
+
+    #[test]
+    fn test_scripted_run() {
+        let world = World::builder()
+            .t(10)
+            .battery([false, false])
+            .hot_bath([true, true, false])
+            .cold_bath([false, false])
+            .build();
+        let mut sim = Simulation::new(world, vec![Box::new(CondSwap)]);
+        // The first step moves one quantum to the battery and one to the
+        // cold bath; the second moves them back.
+        sim.step();
+        let report = sim.report();
+        assert_eq!(report.start_t, 10);
+        assert_eq!(report.final_t, 11);
+        assert_eq!(
+            report.occupations,
+            [
+                ("battery".to_string(), 1, 2),
+                ("hot".to_string(), 0, 3),
+                ("cold".to_string(), 1, 2),
+            ]
+        );
+        assert_eq!(report.work_extracted, 1);
+        assert_eq!(report.net_heat, [1, -2, 1]);
+        assert_eq!(report.efficiency, Some(0.5));
+
+        sim.step();
+        let report = sim.report();
+        assert_eq!(report.final_t, 12);
+        assert_eq!(report.work_extracted, 0);
+        assert_eq!(report.net_heat, [0, 0, 0]);
+        assert_eq!(report.efficiency, None);
+    }
+
+    #[test]
+    fn test_display() {
+        let report = RunReport {
+            start_t: 0,
+            final_t: 100,
+            occupations: vec![("battery".to_string(), 3, 10), ("hot".to_string(), 5, 20)],
+            work_extracted: 3,
+            net_heat: vec![3, -4],
+            efficiency: Some(0.75),
+            elapsed: Duration::from_millis(1500),
+        };
+        assert_eq!(
+            report.to_string(),
+            "t: 0 -> 100 in 1.50s\n\
+             battery: 3/10 occupied, net +3\n\
+             hot: 5/20 occupied, net -4\n\
+             work extracted: 3\n\
+             efficiency: 0.7500"
+        );
+        let report = RunReport {
+            efficiency: None,
+            ..report
+        };
+        assert!(report.to_string().ends_with("efficiency: n/a"));
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt::Display;
+
+use crate::World;
+
+/// The energy contents of a world's classic three subsystems at time `t`,
+/// without the cells themselves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Snapshot {
+    pub t: i64,
+    pub battery: usize,
+    pub hot: usize,
+    pub cold: usize,
+}
+
+/// Signed change from one snapshot to another.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct SnapshotDiff {
+    pub dt: i64,
+    pub battery: i64,
+    pub hot: i64,
+    pub cold: i64,
+}
+
+impl From<&World> for Snapshot {
+    fn from(world: &World) -> Snapshot {
+        Snapshot {
+            t: world.t,
+            battery: world.battery_energy(),
+            hot: world.hot_bath_energy(),
+            cold: world.cold_bath_energy(),
+        }
+    }
+}
+
+impl Snapshot {
+    /// How `later` differs from `self`.
+    pub fn diff(&self, later: &Snapshot) -> SnapshotDiff {
+        SnapshotDiff {
+            dt: later.t - self.t,
+            battery: later.battery as i64 - self.battery as i64,
+            hot: later.hot as i64 - self.hot as i64,
+            cold: later.cold as i64 - self.cold as i64,
+        }
+    }
+}
+
+impl Display for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "t={} battery={} hot={} cold={}",
+            self.t, self.battery, self.hot, self.cold
+        )
+    }
+}
+
+impl Display for SnapshotDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "dt={:+} battery={:+} hot={:+} cold={:+}",
+            self.dt, self.battery, self.hot, self.cold
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{CondSwap, Rule};
+
+    fn world() -> World {
+        World::builder()
+            .t(5)
+            .battery([false, true])
+            .hot_bath([true, true, false])
+            .cold_bath([false, false])
+            .build()
+    }
+
+    #[test]
+    fn test_from_world() {
+        let snapshot = Snapshot::from(&world());
+        assert_eq!(
+            snapshot,
+            Snapshot {
+                t: 5,
+                battery: 1,
+                hot: 2,
+                cold: 0,
+            }
+        );
+        assert_eq!(snapshot.to_string(), "t=5 battery=1 hot=2 cold=0");
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut world = world();
+        let before = Snapshot::from(&world);
+        CondSwap.step(&mut world);
+        world.t += 1;
+        let diff = before.diff(&Snapshot::from(&world));
+        assert_eq!(
+            diff,
+            SnapshotDiff {
+                dt: 1,
+                battery: 1,
+                hot: -2,
+                cold: 1,
+            }
+        );
+        assert_eq!(diff.to_string(), "dt=+1 battery=+1 hot=-2 cold=+1");
+        assert_eq!(before.diff(&before), SnapshotDiff::default());
+    }
+}
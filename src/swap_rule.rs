@@ -0,0 +1,110 @@
+/// Declares a unit struct implementing `Rule` as "if the cells match one of
+/// these patterns, make these swaps", e.g.
+///
+/// ```
+/// use generalized_heat_engine::{swap_rule, BathId};
+///
+/// swap_rule! {
+///     /// Moves a quantum from hot cell 0 to cold cell 0 and back.
+///     pub struct Leak;
+///     when (BathId::Hot, 0, true), (BathId::Cold, 0, false);
+///     when (BathId::Hot, 0, false), (BathId::Cold, 0, true);
+///     swap (BathId::Hot, 0), (BathId::Cold, 0);
+/// }
+/// ```
+///
+/// Each `when` line is a pattern of `(subsystem, index, expected)`
+/// conditions that must all hold; the rule fires if any pattern matches,
+/// and then makes every `swap` in order. Its footprint is every cell
+/// mentioned, and `validate` checks they all exist.
+///
+/// The generated rule is its own inverse, which is only right if the swaps
+/// take every state matching a pattern to another state matching one (and
+/// undo themselves, as swaps of distinct cells do). Otherwise stepping
+/// backward won't undo stepping forward; check with `assert_reversible`.
+#[macro_export]
+macro_rules! swap_rule {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident;
+        $(when $(($wid:expr, $wi:expr, $wv:expr)),+ ;)+
+        $(swap ($aid:expr, $ai:expr), ($bid:expr, $bi:expr);)+
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+        $vis struct $name;
+
+        impl $crate::Rule for $name {
+            fn step(&self, world: &mut $crate::World) {
+                if false $(|| (true $(&& world.get(($wid, $wi)) == $wv)+))+ {
+                    $(world.swap_cells(($aid, $ai), ($bid, $bi));)+
+                }
+            }
+
+            fn inverse(&self) -> ::std::boxed::Box<dyn $crate::Rule> {
+                ::std::boxed::Box::new(*self)
+            }
+
+            fn clone_box(&self) -> ::std::boxed::Box<dyn $crate::Rule> {
+                ::std::boxed::Box::new(*self)
+            }
+
+            fn validate(&self, world: &$crate::World) -> ::std::result::Result<(), $crate::RuleError> {
+                $crate::Rule::footprint(self).check(stringify!($name), world)
+            }
+
+            fn footprint(&self) -> $crate::Footprint {
+                $crate::Footprint::from_cells([
+                    $($(($wid, $wi),)+)+
+                    $(($aid, $ai), ($bid, $bi),)+
+                ])
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use crate::*;
+
+    swap_rule! {
+        /// `CondSwap`, spelled out.
+        struct MacroCondSwap;
+        when (BathId::Hot, 0, true), (BathId::Hot, 1, true), (BathId::Cold, 0, false), (BathId::Battery, 0, false);
+        when (BathId::Hot, 0, false), (BathId::Hot, 1, false), (BathId::Cold, 0, true), (BathId::Battery, 0, true);
+        swap (BathId::Hot, 0), (BathId::Cold, 0);
+        swap (BathId::Hot, 1), (BathId::Battery, 0);
+    }
+
+    #[test]
+    fn test_matches_cond_swap() {
+        for bits in 0..32u8 {
+            let bit = |i: u8| bits >> i & 1 == 1;
+            let world = World::classic([bit(0)], [bit(1), bit(2), bit(3)], [bit(4)]);
+            let (mut a, mut b) = (world.clone(), world.clone());
+            MacroCondSwap.step(&mut a);
+            CondSwap.step(&mut b);
+            assert_eq!(a, b);
+            assert_reversible(&MacroCondSwap, &world, 3);
+        }
+        assert_eq!(MacroCondSwap.footprint(), CondSwap.footprint());
+    }
+
+    #[test]
+    fn test_validate() {
+        let world = World::classic([true], [true], [false]);
+        assert_eq!(
+            MacroCondSwap.validate(&world),
+            Err(RuleError::OutOfRange {
+                rule: "MacroCondSwap",
+                subsystem: BathId::Hot,
+                index: 1,
+                len: 1,
+            })
+        );
+        assert_eq!(
+            MacroCondSwap.validate(&World::classic([true], [true, true], [false])),
+            Ok(())
+        );
+    }
+}
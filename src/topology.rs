@@ -0,0 +1,92 @@
+use std::collections::BTreeSet;
+
+use crate::{BathId, Rule, RuleError, World};
+
+/// Which pairs of subsystems rules may exchange energy between, e.g. an
+/// engine whose battery only ever touches the hot bath.
+///
+/// A rule couples every pair of subsystems its `Rule::footprint` touches.
+/// That's checked per rule in a rule set, so a composite rule is held to
+/// the union of its parts: keep differently-wired rules separate. Rules
+/// that report no footprint pass unchecked.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Topology {
+    /// Each pair with the lower `BathId` first.
+    pairs: BTreeSet<(BathId, BathId)>,
+}
+
+impl Topology {
+    /// No couplings at all: each rule may only touch one subsystem.
+    pub fn new() -> Topology {
+        Topology::default()
+    }
+
+    /// Also allows exchange between `a` and `b`, in either direction.
+    pub fn allow(mut self, a: BathId, b: BathId) -> Self {
+        self.pairs.insert((a.min(b), a.max(b)));
+        self
+    }
+
+    /// A subsystem is always coupled to itself.
+    pub fn allows(&self, a: BathId, b: BathId) -> bool {
+        a == b || self.pairs.contains(&(a.min(b), a.max(b)))
+    }
+
+    /// Checks each of `rules` against the topology, reporting the first
+    /// that couples a disallowed pair by its position in `rules`.
+    pub fn check(&self, rules: &[Box<dyn Rule>], world: &World) -> Result<(), RuleError> {
+        for (position, rule) in rules.iter().enumerate() {
+            let baths: Vec<BathId> = rule.footprint().baths(world).into_iter().collect();
+            for (i, &a) in baths.iter().enumerate() {
+                if let Some(&b) = baths[i + 1..].iter().find(|&&b| !self.allows(a, b)) {
+                    return Err(RuleError::Uncoupled { position, a, b });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{CondSwap, ProbeAndSwap, ReflectBath, WeirdPermute};
+
+    fn battery_hot() -> Topology {
+        Topology::new().allow(BathId::Hot, BathId::Battery)
+    }
+
+    #[test]
+    fn test_allows() {
+        let topology = battery_hot();
+        assert!(topology.allows(BathId::Battery, BathId::Hot));
+        assert!(topology.allows(BathId::Hot, BathId::Battery));
+        assert!(topology.allows(BathId::Cold, BathId::Cold));
+        assert!(!topology.allows(BathId::Hot, BathId::Cold));
+        assert!(!Topology::new().allows(BathId::Battery, BathId::Hot));
+    }
+
+    #[test]
+    fn test_check() {
+        let world = World::new(4, 8);
+        let rules: Vec<Box<dyn Rule>> = vec![
+            Box::new(ProbeAndSwap::default()),
+            Box::new(ReflectBath {
+                subsystem: BathId::Cold,
+            }),
+        ];
+        assert_eq!(battery_hot().check(&rules, &world), Ok(()));
+        // CondSwap moves energy between all three.
+        let rules: Vec<Box<dyn Rule>> = vec![Box::new(ProbeAndSwap::default()), Box::new(CondSwap)];
+        assert_eq!(
+            battery_hot().check(&rules, &world),
+            Err(RuleError::Uncoupled {
+                position: 1,
+                a: BathId::Battery,
+                b: BathId::Cold,
+            })
+        );
+        let rules: Vec<Box<dyn Rule>> = vec![Box::new(WeirdPermute::new(1))];
+        assert!(battery_hot().check(&rules, &world).is_err());
+    }
+}
@@ -0,0 +1,261 @@
+use std::io::{self, Write};
+
+use crate::World;
+
+/// Which timesteps a [`Trajectory`] keeps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sampling {
+    EveryStep,
+    /// `t = 0` and every `t` whose magnitude is a power of two, like
+    /// `main`'s printout.
+    PowersOfTwo,
+    Every(u64),
+}
+
+impl Sampling {
+    pub fn includes(&self, t: i64) -> bool {
+        match *self {
+            Sampling::EveryStep => true,
+            Sampling::PowersOfTwo => t == 0 || t.unsigned_abs().is_power_of_two(),
+            Sampling::Every(k) => k != 0 && t.rem_euclid(k as i64) == 0,
+        }
+    }
+}
+
+/// Picks out the same steps as `Sampling::PowersOfTwo` (0, 1, 2, 4, ...)
+/// from a run's elapsed step count, by keeping the next checkpoint and
+/// comparing against it rather than testing every step.
+///
+/// Counts passed to `sample` must never decrease; they may skip ahead, as
+/// when stepping in batches, in which case skipped checkpoints are missed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PowerOfTwoSampler {
+    next: u64,
+}
+
+impl PowerOfTwoSampler {
+    pub fn new() -> PowerOfTwoSampler {
+        PowerOfTwoSampler::default()
+    }
+
+    /// Whether `elapsed` is a checkpoint.
+    pub fn sample(&mut self, elapsed: u64) -> bool {
+        if elapsed < self.next {
+            return false;
+        }
+        if elapsed > self.next {
+            self.next = elapsed.checked_next_power_of_two().unwrap_or(u64::MAX);
+        }
+        if elapsed != self.next {
+            return false;
+        }
+        self.next = if elapsed == 0 {
+            1
+        } else {
+            elapsed.saturating_mul(2)
+        };
+        true
+    }
+}
+
+/// Energy observables `(t, battery, hot, cold)` recorded over a run.
+#[derive(Clone, Debug)]
+pub struct Trajectory {
+    sampling: Sampling,
+    samples: Vec<(i64, usize, usize, usize)>,
+    /// Battery, hot, and cold lengths as of the last push, for `to_json`.
+    sizes: (usize, usize, usize),
+}
+
+impl Trajectory {
+    pub fn new(sampling: Sampling) -> Trajectory {
+        Trajectory {
+            sampling,
+            samples: Vec::new(),
+            sizes: (0, 0, 0),
+        }
+    }
+
+    /// Records `world` if its `t` is one the sampling keeps.
+    pub fn push(&mut self, world: &World) {
+        if self.sampling.includes(world.t) {
+            self.sizes = (
+                world.battery().len(),
+                world.hot_bath().len(),
+                world.cold_bath().len(),
+            );
+            self.samples.push((
+                world.t,
+                world.battery_energy(),
+                world.hot_bath_energy(),
+                world.cold_bath_energy(),
+            ));
+        }
+    }
+
+    pub fn samples(&self) -> &[(i64, usize, usize, usize)] {
+        &self.samples
+    }
+
+    pub fn to_csv(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(out, "t,battery,hot,cold")?;
+        for (t, battery, hot, cold) in &self.samples {
+            writeln!(out, "{t},{battery},{hot},{cold}")?;
+        }
+        Ok(())
+    }
+
+    /// The samples as a JSON array of `{"t", "battery", "hot", "cold"}`
+    /// objects, each subsystem given as its occupied fraction (zero for an
+    /// empty subsystem) at full `f64` precision.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        #[derive(serde::Serialize)]
+        struct Row {
+            t: i64,
+            battery: f64,
+            hot: f64,
+            cold: f64,
+        }
+        let fraction = |energy: usize, len: usize| match len {
+            0 => 0.0,
+            len => energy as f64 / len as f64,
+        };
+        let (battery_len, hot_len, cold_len) = self.sizes;
+        let rows: Vec<Row> = self
+            .samples
+            .iter()
+            .map(|&(t, battery, hot, cold)| Row {
+                t,
+                battery: fraction(battery, battery_len),
+                hot: fraction(hot, hot_len),
+                cold: fraction(cold, cold_len),
+            })
+            .collect();
+        serde_json::to_string(&rows).expect("plain numbers always serialize")
+    }
+}
+
+/// A horizontal bar chart with one line per `(t, energy)` sample, e.g.
+/// `  16 |###       | 3`. Bars are scaled so `max` fills `width` columns;
+/// larger energies are clamped, and a `max` of zero draws empty bars.
+pub fn render_timeline(samples: &[(i64, usize)], max: usize, width: usize) -> String {
+    let t_width = samples
+        .iter()
+        .map(|(t, _)| t.to_string().len())
+        .max()
+        .unwrap_or(0);
+    samples
+        .iter()
+        .map(|&(t, energy)| {
+            let filled = (energy.min(max) * width + max / 2)
+                .checked_div(max)
+                .unwrap_or(0);
+            format!(
+                "{t:>t_width$} |{bar:<width$}| {energy}",
+                bar = "#".repeat(filled)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{CondSwap, Rule};
+
+    #[test]
+    fn test_sampling() {
+        let ts = |s: Sampling| (-3..=9).filter(|&t| s.includes(t)).collect::<Vec<_>>();
+        assert_eq!(ts(Sampling::EveryStep), (-3..=9).collect::<Vec<_>>());
+        assert_eq!(ts(Sampling::PowersOfTwo), vec![-2, -1, 0, 1, 2, 4, 8]);
+        assert_eq!(ts(Sampling::Every(3)), vec![-3, 0, 3, 6, 9]);
+    }
+
+    #[test]
+    fn test_to_csv() {
+        let mut world = World::builder()
+            .battery([false])
+            .hot_bath([true, true])
+            .cold_bath([false])
+            .build();
+        let mut trajectory = Trajectory::new(Sampling::EveryStep);
+        for _ in 0..2 {
+            trajectory.push(&world);
+            CondSwap.step(&mut world);
+            world.t += 1;
+        }
+        let mut out = Vec::new();
+        trajectory.to_csv(&mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "t,battery,hot,cold\n0,0,2,0\n1,1,0,1\n"
+        );
+    }
+
+    #[test]
+    fn test_power_of_two_sampler() {
+        let mut sampler = PowerOfTwoSampler::new();
+        let sampled: Vec<u64> = (0..=40).filter(|&i| sampler.sample(i)).collect();
+        assert_eq!(sampled, [0, 1, 2, 4, 8, 16, 32]);
+        assert!(sampled
+            .iter()
+            .all(|&i| Sampling::PowersOfTwo.includes(i as i64)));
+
+        let mut sampler = PowerOfTwoSampler::new();
+        let sampled: Vec<u64> = (5..=40).filter(|&i| sampler.sample(i)).collect();
+        assert_eq!(sampled, [8, 16, 32]);
+
+        // Batches of 8 land on every checkpoint from 8 on.
+        let mut sampler = PowerOfTwoSampler::new();
+        let sampled: Vec<u64> = (0..=64).step_by(8).filter(|&i| sampler.sample(i)).collect();
+        assert_eq!(sampled, [0, 8, 16, 32, 64]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_to_json() {
+        let mut world = World::builder()
+            .battery([false])
+            .hot_bath([true, true, false, false])
+            .cold_bath([false, false])
+            .build();
+        let mut trajectory = Trajectory::new(Sampling::EveryStep);
+        for _ in 0..2 {
+            trajectory.push(&world);
+            CondSwap.step(&mut world);
+            world.t += 1;
+        }
+        let parsed: serde_json::Value = serde_json::from_str(&trajectory.to_json()).unwrap();
+        assert_eq!(
+            parsed,
+            serde_json::json!([
+                {"t": 0, "battery": 0.0, "hot": 0.5, "cold": 0.0},
+                {"t": 1, "battery": 1.0, "hot": 0.0, "cold": 0.5},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_render_timeline() {
+        let samples = [(0, 0), (1, 1), (2, 2), (4, 4), (128, 5)];
+        assert_eq!(
+            render_timeline(&samples, 4, 8),
+            concat!(
+                "  0 |        | 0\n",
+                "  1 |##      | 1\n",
+                "  2 |####    | 2\n",
+                "  4 |########| 4\n",
+                "128 |########| 5",
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_timeline_degenerate() {
+        assert_eq!(render_timeline(&[], 10, 5), "");
+        assert_eq!(render_timeline(&[(3, 2)], 0, 4), "3 |    | 2");
+        assert_eq!(render_timeline(&[(3, 2)], 4, 0), "3 || 2");
+    }
+}
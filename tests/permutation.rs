@@ -0,0 +1,82 @@
+use generalized_heat_engine::{
+    compose_permutations, invert_permutation, permute, permute_in_place,
+};
+use proptest::prelude::*;
+
+/// A vector of up to 64 arbitrary elements together with a permutation of
+/// its indices.
+fn permuted_vec<T: Arbitrary + Clone>() -> impl Strategy<Value = (Vec<T>, Vec<usize>)> {
+    prop::collection::vec(any::<T>(), 0..64).prop_flat_map(|xs| {
+        let permutation = Just((0..xs.len()).collect::<Vec<_>>()).prop_shuffle();
+        (Just(xs), permutation)
+    })
+}
+
+fn two_permutations() -> impl Strategy<Value = (Vec<String>, Vec<usize>, Vec<usize>)> {
+    permuted_vec::<String>().prop_flat_map(|(xs, a)| {
+        let b = Just((0..xs.len()).collect::<Vec<_>>()).prop_shuffle();
+        (Just(xs), Just(a), b)
+    })
+}
+
+proptest! {
+    #[test]
+    fn test_invert_round_trip_bool((xs, p) in permuted_vec::<bool>()) {
+        let mut ys = xs.clone();
+        permute(&p, &mut ys);
+        permute(&invert_permutation(&p), &mut ys);
+        prop_assert_eq!(ys, xs);
+    }
+
+    #[test]
+    fn test_invert_round_trip_string((xs, p) in permuted_vec::<String>()) {
+        let mut ys = xs.clone();
+        permute(&p, &mut ys);
+        permute(&invert_permutation(&p), &mut ys);
+        prop_assert_eq!(ys, xs);
+    }
+
+    #[test]
+    fn test_invert_is_involution((_, p) in permuted_vec::<()>()) {
+        prop_assert_eq!(invert_permutation(&invert_permutation(&p)), p);
+    }
+
+    #[test]
+    fn test_compose((xs, a, b) in two_permutations()) {
+        let mut stepwise = xs.clone();
+        permute(&a, &mut stepwise);
+        permute(&b, &mut stepwise);
+        let mut composed = xs;
+        permute(&compose_permutations(&a, &b), &mut composed);
+        prop_assert_eq!(stepwise, composed);
+    }
+
+    #[test]
+    fn test_in_place_matches_permute((xs, p) in permuted_vec::<String>()) {
+        let mut expected = xs.clone();
+        permute(&p, &mut expected);
+        let mut in_place = xs;
+        permute_in_place(&p, &mut in_place);
+        prop_assert_eq!(in_place, expected);
+    }
+
+    #[test]
+    fn test_in_place_with_fixed_points(
+        (xs, p) in permuted_vec::<u32>(),
+        fixed in prop::collection::vec(any::<bool>(), 64),
+    ) {
+        // Pin the chosen indices in place, and shuffle the rest among
+        // themselves.
+        let moving: Vec<usize> = (0..p.len()).filter(|&i| !fixed[i]).collect();
+        let mut q: Vec<usize> = (0..p.len()).collect();
+        let shuffled: Vec<usize> = p.iter().copied().filter(|&i| !fixed[i]).collect();
+        for (&i, &j) in moving.iter().zip(&shuffled) {
+            q[i] = j;
+        }
+        let mut expected = xs.clone();
+        permute(&q, &mut expected);
+        let mut in_place = xs;
+        permute_in_place(&q, &mut in_place);
+        prop_assert_eq!(in_place, expected);
+    }
+}
@@ -0,0 +1,20 @@
+use generalized_heat_engine::{CondSwap, Rule, SimConfig, WeirdPermute, World};
+
+#[test]
+fn test_default_rules_reverse() {
+    let start = World::from_config(&SimConfig::default());
+    let rules: Vec<Box<dyn Rule>> = vec![Box::new(CondSwap), Box::new(WeirdPermute::new(0))];
+    let inv_rules = rules.inverse();
+
+    let mut world = start.clone();
+    for _ in 0..1000 {
+        rules.step(&mut world);
+        world.t += 1;
+    }
+    assert_ne!(world, start);
+    for _ in 0..1000 {
+        inv_rules.step(&mut world);
+        world.t -= 1;
+    }
+    assert_eq!(world, start);
+}